@@ -1,20 +1,141 @@
 use anyhow::{Context, Result};
-use image::{codecs::png::PngEncoder, ColorType, ImageEncoder};
+use clap::{error::ErrorKind, CommandFactory, Parser, ValueEnum};
+use image::{ExtendedColorType, ImageFormat};
 use num::Complex;
-use std::{env, fs::File};
-
-fn get_args() -> Result<Vec<String>> {
-    let mut args = env::args();
-    Ok(vec![
-        args.next().context("Failed to get arg: cmd")?,
-        args.next().context("Failed to get arg: filename")?,
-        args.next()
-            .context("Failed to get arg: bound; {width}x{height}")?,
-        args.next()
-            .context("Failed to get arg: upper left; {re},{im}")?,
-        args.next()
-            .context("Failed to get arg: lower right; {re},{im}")?,
-    ])
+use std::{num::NonZeroUsize, path::PathBuf, str::FromStr};
+
+#[derive(Parser, Debug)]
+#[command(version, about = None, long_about =
+    "Render the Mandelbrot set as a grayscale image.\n\
+    The view is either --upper-left with --lower-right, or --center with --zoom\n\
+    (the height follows the image's aspect ratio).\n\
+    e.g. mandelbrot -s 4000x3000 --upper-left=-1.2,0.35 --lower-right=-1.0,0.2 -o mandel.png"
+)]
+struct Args {
+    /// Image size in pixels, WIDTHxHEIGHT
+    #[arg(short, long, value_parser = parse_size, default_value = "1000x750")]
+    size: (u32, u32),
+
+    /// Upper left corner of the view, RE,IM
+    #[arg(long, value_parser = parse_complex, allow_hyphen_values = true,
+        requires = "lower_right", conflicts_with_all = ["center", "zoom"])]
+    upper_left: Option<Complex<f64>>,
+
+    /// Lower right corner of the view, RE,IM
+    #[arg(long, value_parser = parse_complex, allow_hyphen_values = true,
+        requires = "upper_left")]
+    lower_right: Option<Complex<f64>>,
+
+    /// Center of the view, RE,IM [default: -0.5,0]
+    #[arg(long, value_parser = parse_complex, allow_hyphen_values = true)]
+    center: Option<Complex<f64>>,
+
+    /// Magnification, where 1 shows 4 units of the real axis [default: 1]
+    #[arg(long, value_parser = parse_zoom)]
+    zoom: Option<f64>,
+
+    /// Iterations before a point counts as in the set
+    #[arg(short, long, default_value_t = NonZeroUsize::new(255).unwrap())]
+    iterations: NonZeroUsize,
+
+    /// Threads to render with, at most one per row [default: available cores]
+    #[arg(short, long)]
+    threads: Option<NonZeroUsize>,
+
+    /// Image path
+    #[arg(short, long, default_value = "mandelbrot.png")]
+    output: PathBuf,
+
+    /// Image format [default: from the output extension]
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+    Png,
+    Jpeg,
+    Bmp,
+    Tiff,
+    Pnm,
+}
+
+impl From<Format> for ImageFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Png => ImageFormat::Png,
+            Format::Jpeg => ImageFormat::Jpeg,
+            Format::Bmp => ImageFormat::Bmp,
+            Format::Tiff => ImageFormat::Tiff,
+            Format::Pnm => ImageFormat::Pnm,
+        }
+    }
+}
+
+// "{a}{sep}{b}", e.g. "4000x3000" or "-1.2,0.35"
+fn parse_pair<N: FromStr>(s: &str, sep: char, what: &str) -> Result<(N, N), String> {
+    let (a, b) = s
+        .trim()
+        .split_once(sep)
+        .ok_or_else(|| format!("expected {what}"))?;
+    match (a.trim().parse(), b.trim().parse()) {
+        (Ok(a), Ok(b)) => Ok((a, b)),
+        _ => Err(format!("expected {what}")),
+    }
+}
+
+fn parse_size(s: &str) -> Result<(u32, u32), String> {
+    let (width, height) = parse_pair::<u32>(s, 'x', "WIDTHxHEIGHT, e.g. 4000x3000")?;
+    if width == 0 || height == 0 {
+        return Err("width and height must be at least 1".to_string());
+    }
+    if (width as u64 * height as u64) > isize::MAX as u64 {
+        return Err("too many pixels".to_string());
+    }
+    Ok((width, height))
+}
+
+fn parse_complex(s: &str) -> Result<Complex<f64>, String> {
+    let (re, im) = parse_pair::<f64>(s, ',', "RE,IM, e.g. -1.2,0.35")?;
+    if !re.is_finite() || !im.is_finite() {
+        return Err("RE and IM must be finite".to_string());
+    }
+    Ok(Complex { re, im })
+}
+
+fn parse_zoom(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(zoom) if zoom.is_finite() && zoom > 0.0 => Ok(zoom),
+        Ok(_) => Err("zoom must be a positive number".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+// upper left and lower right corners of the view
+fn view(args: &Args) -> Result<(Complex<f64>, Complex<f64>), String> {
+    if let (Some(ul), Some(lr)) = (args.upper_left, args.lower_right) {
+        if ul.re >= lr.re || ul.im <= lr.im {
+            return Err(format!(
+                "upper left ({ul}) must be left of and above lower right ({lr})"
+            ));
+        }
+        return Ok((ul, lr));
+    }
+    let center = args.center.unwrap_or(Complex { re: -0.5, im: 0.0 });
+    let width = 4.0 / args.zoom.unwrap_or(1.0);
+    let height = width * args.size.1 as f64 / args.size.0 as f64;
+    let ul = Complex {
+        re: center.re - width / 2.0,
+        im: center.im + height / 2.0,
+    };
+    let lr = Complex {
+        re: center.re + width / 2.0,
+        im: center.im - height / 2.0,
+    };
+    if ul.re == lr.re || ul.im == lr.im {
+        return Err(format!("zoom {} is too deep", args.zoom.unwrap_or(1.0)));
+    }
+    Ok((ul, lr))
 }
 
 fn pixel_to_complex(
@@ -46,19 +167,19 @@ fn escape_time(c: Complex<f64>, limit: usize) -> Option<usize> {
     None
 }
 
-fn render(bound: (u32, u32), ul: &Complex<f64>, lr: &Complex<f64>, threads: u8) -> Vec<u8> {
+fn render(
+    bound: (u32, u32),
+    ul: &Complex<f64>,
+    lr: &Complex<f64>,
+    limit: usize,
+    threads: usize,
+) -> Vec<u8> {
     let (width, height) = bound;
-    let mut image: Vec<u8> = vec![0; (width * height) as usize];
-    let chunk_cnt = {
-        let mut temp = width * height / threads as u32;
-        if temp == 0 {
-            temp = 1;
-        }
-        temp
-    } as usize;
-    let image_chunks: Vec<&mut [u8]> = image
-        .chunks_mut(chunk_cnt)
-        .collect();
+    let mut image: Vec<u8> = vec![0; width as usize * height as usize];
+    // a thread gets at least a row, however many were asked for
+    let threads = threads.clamp(1, height as usize);
+    let chunk_cnt = image.len().div_ceil(threads);
+    let image_chunks: Vec<&mut [u8]> = image.chunks_mut(chunk_cnt).collect();
 
     std::thread::scope(|spawner| {
         let mut starting_pixel_idx = 0;
@@ -69,12 +190,13 @@ fn render(bound: (u32, u32), ul: &Complex<f64>, lr: &Complex<f64>, threads: u8)
             spawner.spawn(move || {
                 for (pixel_relative_i, pixel_relative) in chunk.iter_mut().enumerate() {
                     let curr_pixel_idx = starting_pixel_idx + pixel_relative_i;
-                    let row = curr_pixel_idx as u32 / width;
-                    let col = curr_pixel_idx as u32 % width;
+                    let row = (curr_pixel_idx / width as usize) as u32;
+                    let col = (curr_pixel_idx % width as usize) as u32;
 
                     let c = pixel_to_complex((col, row), bound, ul, lr);
-                    let num = match escape_time(c, 255) {
-                        Some(cnt) => (255 - cnt) as u8,
+                    // the faster a point escapes, the brighter it is
+                    let num = match escape_time(c, limit) {
+                        Some(cnt) => (255 - cnt * 255 / limit) as u8,
                         None => 0,
                     };
 
@@ -89,91 +211,119 @@ fn render(bound: (u32, u32), ul: &Complex<f64>, lr: &Complex<f64>, threads: u8)
     image
 }
 
-/// arg0 = filename
-/// arg1 = {width}x{height}
-/// arg2 = {upper_left.re},{upper_left.im}
-/// arg3 = {lower_right.re},{lower_right.im}
 fn main() -> Result<()> {
-    let args = get_args()?;
-
-    if args.len() != 5 {
-        eprintln!(
-            "Usage: {} <file> <pixels> <upper_left> <lower_right>",
-            args[0]
-        );
-        eprintln!("- upper_left : re1,im1");
-        eprintln!("- lower_right: re2,im2");
-        eprintln!("- re1 < re2 && im1 > im2");
-        eprintln!("e.g. {} mandel.png 4000x3000 -1.2,0.35 -1.0,0.2", args[0]);
-        std::process::exit(1);
-    }
+    let args = Args::parse();
 
-    let filename = &args[1];
-
-    let bound = {
-        let mut bound_str = args[2].trim().split('x');
-        let w = bound_str
-            .next()
-            .context("No width of bound arg")?
-            .parse::<u32>()
-            .context("width of bound is not an integer")?;
-        let h = bound_str
-            .next()
-            .context("No height of bound arg")?
-            .parse::<u32>()
-            .context("heigth of bound is not an integer")?;
-        (w, h)
+    let (upper_left, lower_right) = view(&args).unwrap_or_else(|msg| {
+        Args::command()
+            .error(ErrorKind::ValueValidation, msg)
+            .exit()
+    });
+    let format = match args.format {
+        Some(format) => format.into(),
+        None => ImageFormat::from_path(&args.output).unwrap_or_else(|_| {
+            Args::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    format!(
+                        "can't tell the format of {}; add --format",
+                        args.output.display()
+                    ),
+                )
+                .exit()
+        }),
     };
+    let threads = args
+        .threads
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
 
-    let upper_left = {
-        let mut complex = args[3].trim().split(',');
-        let re = complex
-            .next()
-            .context("No real num of upper left arg")?
-            .parse::<f64>()
-            .context("real num of upper left is not a float")?;
-        let im = complex
-            .next()
-            .context("No imaginary num of upper left arg")?
-            .parse::<f64>()
-            .context("imaginary num of upper left is not a float")?;
-        Complex { re, im }
-    };
+    let (width, height) = args.size;
+    let image = render(
+        args.size,
+        &upper_left,
+        &lower_right,
+        args.iterations.get(),
+        threads,
+    );
 
-    let lower_right = {
-        let mut complex = args[4].trim().split(',');
-        let re = complex
-            .next()
-            .context("No real num of upper left arg")?
-            .parse::<f64>()
-            .context("real num of upper left is not a float")?;
-        let im = complex
-            .next()
-            .context("No imaginary num of upper left arg")?
-            .parse::<f64>()
-            .context("imaginary num of upper left is not a float")?;
-        Complex { re, im }
-    };
+    image::save_buffer_with_format(
+        &args.output,
+        &image,
+        width,
+        height,
+        ExtendedColorType::L8,
+        format,
+    )
+    .with_context(|| format!("Failed to write {}", args.output.display()))?;
+
+    Ok(())
+}
 
-    if upper_left.re >= lower_right.re || upper_left.im <= lower_right.im {
-        eprintln!(
-            "Usage: {} <file> <pixels> <upper_left> <lower_right>",
-            args[0]
+#[cfg(test)]
+mod test {
+    use super::{parse_complex, parse_size, render, view, Args};
+    use clap::Parser;
+    use num::Complex;
+    #[test]
+    fn args_test() {
+        assert_eq!(parse_size("4000x3000"), Ok((4000, 3000)));
+        assert!(parse_size("4000").is_err());
+        assert!(parse_size("0x3000").is_err());
+        assert_eq!(
+            parse_complex("-1.2, 0.35"),
+            Ok(Complex { re: -1.2, im: 0.35 })
         );
-        eprintln!("- upper_left : re1,im1");
-        eprintln!("- lower_right: re2,im2");
-        eprintln!("- re1 < re2 && im1 > im2");
-        eprintln!("e.g. {} mandel.png 4000x3000 -1.2,0.35 -1.0,0.2", args[0]);
-        std::process::exit(1);
-    }
+        assert!(parse_complex("inf,0").is_err());
 
-    let image = render(bound, &upper_left, &lower_right, 100);
+        let args = Args::try_parse_from([
+            "mandelbrot",
+            "--upper-left",
+            "-1.2,0.35",
+            "--lower-right",
+            "-1.0,0.2",
+        ])
+        .unwrap();
+        assert_eq!(
+            view(&args),
+            Ok((
+                Complex { re: -1.2, im: 0.35 },
+                Complex { re: -1.0, im: 0.2 }
+            ))
+        );
+        let args = Args::try_parse_from([
+            "mandelbrot",
+            "--upper-left=-1.0,0.2",
+            "--lower-right=-1.2,0.35",
+        ])
+        .unwrap();
+        assert!(view(&args).is_err());
 
-    let file =
-        File::create(filename).with_context(|| format!("Failed to open/create {}", filename))?;
+        // the height follows the aspect ratio
+        let args =
+            Args::try_parse_from(["mandelbrot", "-s", "400x200", "--center=0,1", "--zoom=2"])
+                .unwrap();
+        assert_eq!(
+            view(&args),
+            Ok((Complex { re: -1.0, im: 1.5 }, Complex { re: 1.0, im: 0.5 }))
+        );
 
-    let encoder = PngEncoder::new(file);
-    encoder.write_image(&image[..], bound.0, bound.1, ColorType::L8)?;
+        assert!(Args::try_parse_from(["mandelbrot", "--upper-left=-1,1"]).is_err());
+        assert!(Args::try_parse_from([
+            "mandelbrot",
+            "--upper-left=-1,1",
+            "--lower-right=1,-1",
+            "--zoom=2"
+        ])
+        .is_err());
+        assert!(Args::try_parse_from(["mandelbrot", "--zoom=-1"]).is_err());
+        assert!(Args::try_parse_from(["mandelbrot", "--threads=0"]).is_err());
 
-    Ok(())
+        // more threads than rows render the same image
+        let (ul, lr) = (Complex { re: -2.0, im: 1.0 }, Complex { re: 1.0, im: -1.0 });
+        assert_eq!(
+            render((30, 20), &ul, &lr, 50, usize::MAX),
+            render((30, 20), &ul, &lr, 50, 1)
+        );
+    }
 }
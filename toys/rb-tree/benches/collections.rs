@@ -0,0 +1,396 @@
+// RBTree against BTreeSet, and RBTreeMap against BTreeMap, for u64, f64 and
+// String values at 1e3..1e7 elements
+//   cargo bench --bench collections -- insert/u64
+// runs a part of it, and RB_TREE_BENCH_MAX_LEN=100000 skips the larger sizes.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use rand::prelude::*;
+use rb_tree::{map::RBTreeMap, RBTree};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    hint::black_box,
+};
+
+const LENS: [usize; 5] = [1_000, 10_000, 100_000, 1_000_000, 10_000_000];
+
+trait Value: PartialOrd + Clone + Debug {
+    type Key: Ord + Clone; // the same value as a key of BTreeSet
+    const NAME: &'static str;
+    fn random(rng: &mut StdRng) -> Self;
+    fn key(&self) -> Self::Key;
+}
+
+impl Value for u64 {
+    type Key = u64;
+    const NAME: &'static str = "u64";
+    fn random(rng: &mut StdRng) -> Self {
+        rng.gen()
+    }
+    fn key(&self) -> u64 {
+        *self
+    }
+}
+
+// f64 is not `Ord`, so BTreeSet gets it with the total order
+#[derive(Clone, Copy, PartialEq)]
+struct TotalF64(f64);
+
+impl Eq for TotalF64 {}
+
+impl PartialOrd for TotalF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TotalF64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl Value for f64 {
+    type Key = TotalF64;
+    const NAME: &'static str = "f64";
+    fn random(rng: &mut StdRng) -> Self {
+        rng.gen()
+    }
+    fn key(&self) -> TotalF64 {
+        TotalF64(*self)
+    }
+}
+
+impl Value for String {
+    type Key = String;
+    const NAME: &'static str = "String";
+    fn random(rng: &mut StdRng) -> Self {
+        format!("{:016x}", rng.gen::<u64>())
+    }
+    fn key(&self) -> String {
+        self.clone()
+    }
+}
+
+fn lens() -> impl Iterator<Item = usize> {
+    let max_len = std::env::var("RB_TREE_BENCH_MAX_LEN")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(usize::MAX);
+    LENS.into_iter().filter(move |&len| len <= max_len)
+}
+
+// len random values in random order, and the same values sorted
+fn values<V: Value>(len: usize) -> (Vec<V>, Vec<V>) {
+    let mut rng = StdRng::seed_from_u64(len as u64);
+    let values: Vec<V> = (0..len).map(|_| V::random(&mut rng)).collect();
+    let mut sorted = values.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    (values, sorted)
+}
+
+fn keys<V: Value>(values: &[V]) -> Vec<V::Key> {
+    values.iter().map(V::key).collect()
+}
+
+// fewer samples for the larger sizes, which take seconds per iteration
+fn sample_size(len: usize) -> usize {
+    if len >= 1_000_000 {
+        10
+    } else {
+        50
+    }
+}
+
+fn bench_insert<V: Value>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("insert/{}", V::NAME));
+    for len in lens() {
+        let (values, _) = values::<V>(len);
+        let keys = keys(&values);
+        group.sample_size(sample_size(len));
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_function(format!("RBTree/{len}"), |b| {
+            b.iter_batched(
+                || values.clone(),
+                |values| {
+                    let mut rbt = RBTree::new();
+                    for v in values {
+                        rbt.insert(v);
+                    }
+                    rbt
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_function(format!("BTreeSet/{len}"), |b| {
+            b.iter_batched(
+                || keys.clone(),
+                |keys| {
+                    let mut set = BTreeSet::new();
+                    for k in keys {
+                        set.insert(k);
+                    }
+                    set
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_find<V: Value>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("find/{}", V::NAME));
+    for len in lens() {
+        let (values, sorted) = values::<V>(len);
+        let keys = keys(&values);
+        let rbt = RBTree::from_sorted_iter(sorted.iter().cloned());
+        let set: BTreeSet<V::Key> = keys.iter().cloned().collect();
+        group.sample_size(sample_size(len));
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_function(format!("RBTree/{len}"), |b| {
+            b.iter(|| values.iter().filter(|v| rbt.find(v)).count())
+        });
+        group.bench_function(format!("BTreeSet/{len}"), |b| {
+            b.iter(|| keys.iter().filter(|k| set.contains(k)).count())
+        });
+    }
+    group.finish();
+}
+
+fn bench_delete<V: Value>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("delete/{}", V::NAME));
+    for len in lens() {
+        let (values, sorted) = values::<V>(len);
+        let keys = keys(&values);
+        group.sample_size(sample_size(len));
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_function(format!("RBTree/{len}"), |b| {
+            b.iter_batched(
+                || RBTree::from_sorted_iter(sorted.iter().cloned()),
+                |mut rbt| {
+                    for v in &values {
+                        rbt.delete(v);
+                    }
+                    rbt
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_function(format!("BTreeSet/{len}"), |b| {
+            b.iter_batched(
+                || keys.iter().cloned().collect::<BTreeSet<_>>(),
+                |mut set| {
+                    for k in &keys {
+                        set.remove(k);
+                    }
+                    set
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Insert,
+    Delete,
+    Find,
+}
+
+// len ops on a set of len values: half finds, a quarter each of inserts and deletes
+fn bench_mixed<V: Value>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("mixed/{}", V::NAME));
+    for len in lens() {
+        let (values, sorted) = values::<V>(len);
+        let mut rng = StdRng::seed_from_u64(0);
+        let ops: Vec<(Op, V)> = (0..len)
+            .map(|_| {
+                let op = [Op::Find, Op::Find, Op::Insert, Op::Delete][rng.gen_range(0..4)];
+                let v = match op {
+                    Op::Insert => V::random(&mut rng),
+                    _ => values[rng.gen_range(0..len)].clone(),
+                };
+                (op, v)
+            })
+            .collect();
+        let key_ops: Vec<(Op, V::Key)> = ops.iter().map(|(op, v)| (*op, v.key())).collect();
+        group.sample_size(sample_size(len));
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_function(format!("RBTree/{len}"), |b| {
+            b.iter_batched(
+                || RBTree::from_sorted_iter(sorted.iter().cloned()),
+                |mut rbt| {
+                    for (op, v) in &ops {
+                        match op {
+                            Op::Insert => black_box(rbt.insert(v.clone())),
+                            Op::Delete => black_box(rbt.delete(v)),
+                            Op::Find => black_box(rbt.find(v)),
+                        };
+                    }
+                    rbt
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_function(format!("BTreeSet/{len}"), |b| {
+            b.iter_batched(
+                || sorted.iter().map(V::key).collect::<BTreeSet<_>>(),
+                |mut set| {
+                    for (op, k) in &key_ops {
+                        match op {
+                            Op::Insert => black_box(set.insert(k.clone())),
+                            Op::Delete => black_box(set.remove(k)),
+                            Op::Find => black_box(set.contains(k)),
+                        };
+                    }
+                    set
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_iter<V: Value>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("iter/{}", V::NAME));
+    for len in lens() {
+        let (_, sorted) = values::<V>(len);
+        let rbt = RBTree::from_sorted_iter(sorted.iter().cloned());
+        let set: BTreeSet<V::Key> = sorted.iter().map(V::key).collect();
+        group.sample_size(sample_size(len));
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_function(format!("RBTree/{len}"), |b| {
+            b.iter(|| rbt.iter().map(black_box).count())
+        });
+        group.bench_function(format!("BTreeSet/{len}"), |b| {
+            b.iter(|| set.iter().map(black_box).count())
+        });
+    }
+    group.finish();
+}
+
+// values as keys mapped to their index
+fn bench_map_insert<V: Value>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("map_insert/{}", V::NAME));
+    for len in lens() {
+        let (values, _) = values::<V>(len);
+        let keys = keys(&values);
+        group.sample_size(sample_size(len));
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_function(format!("RBTreeMap/{len}"), |b| {
+            b.iter_batched(
+                || values.clone(),
+                |values| {
+                    let mut map = RBTreeMap::new();
+                    for (i, v) in values.into_iter().enumerate() {
+                        map.insert(v, i);
+                    }
+                    map
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_function(format!("BTreeMap/{len}"), |b| {
+            b.iter_batched(
+                || keys.clone(),
+                |keys| {
+                    let mut map = BTreeMap::new();
+                    for (i, k) in keys.into_iter().enumerate() {
+                        map.insert(k, i);
+                    }
+                    map
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_map_get<V: Value>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("map_get/{}", V::NAME));
+    for len in lens() {
+        let (values, _) = values::<V>(len);
+        let keys = keys(&values);
+        let mut rbt_map = RBTreeMap::new();
+        for (i, v) in values.iter().enumerate() {
+            rbt_map.insert(v.clone(), i);
+        }
+        let map: BTreeMap<V::Key, usize> = keys.iter().cloned().zip(0..).collect();
+        group.sample_size(sample_size(len));
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_function(format!("RBTreeMap/{len}"), |b| {
+            b.iter(|| values.iter().filter_map(|v| rbt_map.get(v)).sum::<usize>())
+        });
+        group.bench_function(format!("BTreeMap/{len}"), |b| {
+            b.iter(|| keys.iter().filter_map(|k| map.get(k)).sum::<usize>())
+        });
+    }
+    group.finish();
+}
+
+// 1000 ranges of about 100 keys each
+fn bench_map_range<V: Value>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("map_range/{}", V::NAME));
+    for len in lens() {
+        let (values, sorted) = values::<V>(len);
+        let mut rbt_map = RBTreeMap::new();
+        for (i, v) in values.iter().enumerate() {
+            rbt_map.insert(v.clone(), i);
+        }
+        let map: BTreeMap<V::Key, usize> = keys(&values).into_iter().zip(0..).collect();
+        let mut rng = StdRng::seed_from_u64(0);
+        let ranges: Vec<(V, V)> = (0..1000)
+            .map(|_| {
+                let start = rng.gen_range(0..len.saturating_sub(100).max(1));
+                let end = (start + 100).min(len - 1);
+                (sorted[start].clone(), sorted[end].clone())
+            })
+            .collect();
+        let key_ranges: Vec<(V::Key, V::Key)> =
+            ranges.iter().map(|(a, b)| (a.key(), b.key())).collect();
+        group.sample_size(sample_size(len));
+        group.throughput(Throughput::Elements(ranges.len() as u64));
+        group.bench_function(format!("RBTreeMap/{len}"), |b| {
+            b.iter(|| {
+                ranges
+                    .iter()
+                    .map(|(a, b)| rbt_map.range(a.clone()..b.clone()).count())
+                    .sum::<usize>()
+            })
+        });
+        group.bench_function(format!("BTreeMap/{len}"), |b| {
+            b.iter(|| {
+                key_ranges
+                    .iter()
+                    .map(|(a, b)| map.range(a..b).count())
+                    .sum::<usize>()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_all<V: Value>(c: &mut Criterion) {
+    bench_insert::<V>(c);
+    bench_find::<V>(c);
+    bench_delete::<V>(c);
+    bench_mixed::<V>(c);
+    bench_iter::<V>(c);
+    bench_map_insert::<V>(c);
+    bench_map_get::<V>(c);
+    bench_map_range::<V>(c);
+}
+
+criterion_group!(
+    benches,
+    bench_all::<u64>,
+    bench_all::<f64>,
+    bench_all::<String>
+);
+criterion_main!(benches);
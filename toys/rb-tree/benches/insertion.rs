@@ -0,0 +1,64 @@
+// same workload as `insertion_test`: 1,000,000 shuffled random f64 values
+
+use rand::prelude::*;
+use rb_tree::RBTree;
+use std::time::{Duration, Instant};
+
+const N: usize = 1000000;
+const ROUNDS: u32 = 5;
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut nums: Vec<f64> = (0..N).map(|_| rng.gen()).collect();
+    nums.shuffle(&mut rng);
+
+    let mut total_insert = Duration::ZERO;
+    let mut total_drop = Duration::ZERO;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        let mut rbt = RBTree::<f64>::new();
+        for n in &nums {
+            rbt.insert(*n);
+        }
+        total_insert += start.elapsed();
+        assert!(rbt.check().0);
+
+        let start = Instant::now();
+        drop(rbt);
+        total_drop += start.elapsed();
+    }
+
+    println!(
+        "insert {} f64: {:?}/round, drop: {:?}/round ({} rounds)",
+        N,
+        total_insert / ROUNDS,
+        total_drop / ROUNDS,
+        ROUNDS
+    );
+
+    // pre-sorted input: one by one vs. O(n) build
+    nums.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mut total_sorted_insert = Duration::ZERO;
+    let mut total_build = Duration::ZERO;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        let mut rbt = RBTree::<f64>::new();
+        for n in &nums {
+            rbt.insert(*n);
+        }
+        total_sorted_insert += start.elapsed();
+
+        let start = Instant::now();
+        let rbt = RBTree::<f64>::from_sorted_iter(nums.iter().cloned());
+        total_build += start.elapsed();
+        assert!(rbt.check().0);
+    }
+
+    println!(
+        "sorted {} f64: insert {:?}/round, from_sorted_iter {:?}/round ({} rounds)",
+        N,
+        total_sorted_insert / ROUNDS,
+        total_build / ROUNDS,
+        ROUNDS
+    );
+}
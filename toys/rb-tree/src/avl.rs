@@ -0,0 +1,277 @@
+use crate::ordered_set::{self, BoxIter, BoxLink, BoxNode, OrderedSet};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+};
+
+// AVL tree: heights of the two subtrees of every node differ by at most 1,
+// which keeps it lower than a red-black tree but rotates more on updates
+pub struct AvlTree<T> {
+    root: BoxLink<T, u32>, // balance of a node is its height
+    cnt: usize,
+}
+
+type Node<T> = Box<BoxNode<T, u32>>;
+
+fn height<T>(link: &BoxLink<T, u32>) -> u32 {
+    link.as_ref().map_or(0, |node| node.balance)
+}
+
+fn update_height<T>(node: &mut Node<T>) {
+    node.balance = 1 + height(&node.lchild).max(height(&node.rchild));
+}
+
+fn rotate_right<T>(mut node: Node<T>) -> Node<T> {
+    let mut lchild = node.lchild.take().unwrap();
+    node.lchild = lchild.rchild.take();
+    update_height(&mut node);
+    lchild.rchild = Some(node);
+    update_height(&mut lchild);
+    lchild
+}
+
+fn rotate_left<T>(mut node: Node<T>) -> Node<T> {
+    let mut rchild = node.rchild.take().unwrap();
+    node.rchild = rchild.lchild.take();
+    update_height(&mut node);
+    rchild.lchild = Some(node);
+    update_height(&mut rchild);
+    rchild
+}
+
+// restores the balance of node after one of its subtrees changed height by 1
+fn rebalance<T>(mut node: Node<T>) -> Node<T> {
+    update_height(&mut node);
+    let (l, r) = (height(&node.lchild), height(&node.rchild));
+    if l > r + 1 {
+        let lchild = node.lchild.take().unwrap();
+        // left-right case
+        node.lchild = Some(if height(&lchild.lchild) < height(&lchild.rchild) {
+            rotate_left(lchild)
+        } else {
+            lchild
+        });
+        rotate_right(node)
+    } else if r > l + 1 {
+        let rchild = node.rchild.take().unwrap();
+        // right-left case
+        node.rchild = Some(if height(&rchild.rchild) < height(&rchild.lchild) {
+            rotate_right(rchild)
+        } else {
+            rchild
+        });
+        rotate_left(node)
+    } else {
+        node
+    }
+}
+
+fn rebalance_link<T>(link: &mut BoxLink<T, u32>) {
+    if let Some(node) = link.take() {
+        *link = Some(rebalance(node));
+    }
+}
+
+fn insert_at<T: PartialOrd>(link: &mut BoxLink<T, u32>, data: T) -> bool {
+    let node = match link {
+        Some(node) => node,
+        None if !ordered_set::comparable(&data) => return false,
+        None => {
+            *link = Some(BoxNode::new(data, 1));
+            return true;
+        }
+    };
+    let inserted = match data.partial_cmp(&node.data) {
+        Some(Ordering::Less) => insert_at(&mut node.lchild, data),
+        Some(Ordering::Greater) => insert_at(&mut node.rchild, data),
+        Some(Ordering::Equal) => {
+            node.cnt += 1;
+            return true;
+        }
+        None => return false,
+    };
+    rebalance_link(link);
+    inserted
+}
+
+// detaches the min node of the subtree, returning it and the rest
+fn take_min<T>(mut node: Node<T>) -> (Node<T>, BoxLink<T, u32>) {
+    match node.lchild.take() {
+        Some(lchild) => {
+            let (min, rest) = take_min(lchild);
+            node.lchild = rest;
+            (min, Some(rebalance(node)))
+        }
+        None => {
+            let rest = node.rchild.take();
+            (node, rest)
+        }
+    }
+}
+
+fn delete_at<T: PartialOrd>(link: &mut BoxLink<T, u32>, data: &T) -> bool {
+    let node = match link {
+        Some(node) => node,
+        None => return false,
+    };
+    match data.partial_cmp(&node.data) {
+        Some(Ordering::Less) => {
+            if !delete_at(&mut node.lchild, data) {
+                return false;
+            }
+        }
+        Some(Ordering::Greater) => {
+            if !delete_at(&mut node.rchild, data) {
+                return false;
+            }
+        }
+        Some(Ordering::Equal) if node.cnt > 1 => {
+            node.cnt -= 1;
+            return true;
+        }
+        Some(Ordering::Equal) => match (node.lchild.take(), node.rchild.take()) {
+            (lchild, None) => {
+                *link = lchild;
+                return true;
+            }
+            (None, rchild) => {
+                *link = rchild;
+                return true;
+            }
+            // replaced by its successor
+            (lchild, Some(rchild)) => {
+                let (mut min, rest) = take_min(rchild);
+                min.lchild = lchild;
+                min.rchild = rest;
+                *link = Some(min);
+            }
+        },
+        None => return false,
+    }
+    rebalance_link(link);
+    true
+}
+
+// checks the stored heights and balance factors; returns the height
+fn check_heights<T>(link: &BoxLink<T, u32>) -> Result<u32, String>
+where
+    T: Debug,
+{
+    let node = match link {
+        Some(node) => node,
+        None => return Ok(0),
+    };
+    let l = check_heights(&node.lchild)?;
+    let r = check_heights(&node.rchild)?;
+    if l.abs_diff(r) > 1 {
+        return Err(format!(
+            "{:?} has subtrees of heights {} and {}",
+            node.data, l, r
+        ));
+    }
+    if node.balance != l.max(r) + 1 {
+        return Err(format!(
+            "{:?} keeps height {}, not {}",
+            node.data,
+            node.balance,
+            l.max(r) + 1
+        ));
+    }
+    Ok(node.balance)
+}
+
+impl<T: Debug> Debug for AvlTree<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(BoxIter::new(&self.root)).finish()
+    }
+}
+
+impl<T: PartialOrd + Debug> Default for AvlTree<T> {
+    fn default() -> Self {
+        AvlTree::new()
+    }
+}
+
+impl<T: PartialOrd + Debug> AvlTree<T> {
+    pub fn new() -> AvlTree<T> {
+        AvlTree { root: None, cnt: 0 }
+    }
+
+    // (is avl or not, height)
+    pub fn check(&self) -> (bool, u32) {
+        match self.validate() {
+            Ok(height) => (true, height),
+            Err(_) => (false, 0),
+        }
+    }
+
+    fn validate(&self) -> Result<u32, String> {
+        let cnt = ordered_set::check_order(&self.root, None, None)?;
+        if cnt != self.cnt {
+            return Err(format!("{} values stored, but len is {}", cnt, self.cnt));
+        }
+        check_heights(&self.root)
+    }
+}
+
+impl<T: PartialOrd + Debug> OrderedSet<T> for AvlTree<T> {
+    type Iter<'a>
+        = BoxIter<'a, T, u32>
+    where
+        T: 'a;
+
+    fn new() -> Self {
+        AvlTree::new()
+    }
+    fn len(&self) -> usize {
+        self.cnt
+    }
+    fn insert(&mut self, data: T) -> bool {
+        let inserted = insert_at(&mut self.root, data);
+        self.cnt += inserted as usize;
+        inserted
+    }
+    fn delete(&mut self, data: &T) -> bool {
+        let deleted = delete_at(&mut self.root, data);
+        self.cnt -= deleted as usize;
+        deleted
+    }
+    fn find(&self, data: &T) -> bool {
+        ordered_set::find_in(&self.root, data)
+    }
+    fn min(&self) -> Option<&T> {
+        ordered_set::min_in(&self.root)
+    }
+    fn max(&self) -> Option<&T> {
+        ordered_set::max_in(&self.root)
+    }
+    fn iter(&self) -> BoxIter<'_, T, u32> {
+        BoxIter::new(&self.root)
+    }
+    fn height(&self) -> u32 {
+        self.check().1
+    }
+    fn check_invariants(&self) -> Result<(), String> {
+        self.validate().map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{avl::AvlTree, ordered_set::OrderedSet};
+    #[test]
+    fn height_test() {
+        // ascending inserts would make a list without rotations
+        let mut avl = AvlTree::<u32>::new();
+        for n in 0..1023 {
+            avl.insert(n);
+        }
+        assert_eq!(avl.check(), (true, 10));
+        for n in (0..1023).step_by(2) {
+            avl.delete(&n);
+        }
+        let (is_avl, height) = avl.check();
+        assert!(is_avl && height <= 10);
+        assert_eq!(format!("{:?}", AvlTree::<u32>::new()), "{}");
+    }
+}
@@ -0,0 +1,21 @@
+// red-black tree (arena of nodes, duplicates kept as counts) and the
+// structures built on it
+pub mod avl;
+pub mod map;
+#[cfg(test)]
+mod model_test;
+pub mod ordered_set;
+pub mod persistent;
+pub mod script;
+pub mod shared;
+pub mod treap;
+pub mod tree;
+
+pub use tree::{
+    cursor::{Cursor, CursorMut, OrderError},
+    dump::DumpValue,
+    entry::CountEntry,
+    set_ops::Counting,
+    Color, IncomparableError, IntoCounts, IntoIter, Iter, MemoryUsage, NodeRef, RBTree, TreeStats,
+    Violation,
+};
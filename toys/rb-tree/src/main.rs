@@ -1,8 +1,76 @@
-mod tree;
+use clap::Parser;
+use color_eyre::eyre::Result;
 use rand::prelude::*;
-use tree::*;
+use rb_tree::{
+    script::{self, Format, Options},
+    RBTree,
+};
+use std::{
+    fs::File,
+    io::{self, BufReader},
+};
 
-fn main() {
+#[derive(Parser, Debug)]
+#[command(version, about = None, long_about =
+    "Replay a script of red-black tree commands, one per line:\n\
+    insert x, delete x, find x, range a b, check, dump\n\
+    (blank lines and lines starting with '#' are skipped).\n\
+    Read standard input when there's no SCRIPT."
+)]
+struct Args {
+    /// Print the tree after every command
+    #[arg(short, long, default_value_t = false)]
+    show: bool,
+
+    /// How `dump` and `--show` print the tree
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Print the equivalent 2-3-4 B-tree next to the tree
+    #[arg(long, default_value_t = false)]
+    btree: bool,
+
+    /// Print the rotations and recolorings done by every insert and delete
+    #[arg(long, default_value_t = false)]
+    trace: bool,
+
+    /// Run the random 10,000 numbers demo instead of a script
+    #[arg(long, default_value_t = false)]
+    demo: bool,
+
+    /// Script path
+    #[clap(value_parser, required = false)]
+    script: Option<String>,
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    let args = Args::parse();
+    if args.demo {
+        demo();
+        return Ok(());
+    }
+
+    let options = Options {
+        format: args.format,
+        show: args.show,
+        btree: args.btree,
+        trace: args.trace,
+    };
+    let mut out = io::stdout().lock();
+    match &args.script {
+        Some(path) => {
+            let file = BufReader::new(File::open(path)?);
+            script::replay(file, &mut out, &options)?;
+        }
+        None => {
+            script::replay(io::stdin().lock(), &mut out, &options)?;
+        }
+    }
+    Ok(())
+}
+
+fn demo() {
     let mut rb_tree = RBTree::<u64>::new();
     let mut rng = rand::thread_rng();
     let mut nums: Vec<u64> = vec![];
@@ -49,9 +117,8 @@ fn main() {
             pass = false;
         }
     }
-    
+
     if pass {
         println!("complete to delete all");
     }
-
 }
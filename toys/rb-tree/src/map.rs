@@ -0,0 +1,359 @@
+use crate::tree::{self, IncomparableError, RBTree};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+    iter::FusedIterator,
+    mem,
+    ops::{Bound, RangeBounds},
+};
+
+// key-value pair stored in the tree, ordered by key only
+struct MapEntry<K, V> {
+    key: K,
+    value: V,
+}
+
+impl<K: PartialEq, V> PartialEq for MapEntry<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: PartialOrd, V> PartialOrd for MapEntry<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.key.partial_cmp(&other.key)
+    }
+}
+
+impl<K: Debug, V> Debug for MapEntry<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.key)
+    }
+}
+
+pub struct RBTreeMap<K: PartialOrd + Debug, V> {
+    tree: RBTree<MapEntry<K, V>>,
+}
+
+impl<K: PartialOrd + Debug, V: Debug> Debug for RBTreeMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: PartialOrd + Debug, V> Default for RBTreeMap<K, V> {
+    fn default() -> Self {
+        RBTreeMap::new()
+    }
+}
+
+impl<K: PartialOrd + Debug, V> RBTreeMap<K, V> {
+    pub fn new() -> RBTreeMap<K, V> {
+        RBTreeMap {
+            tree: RBTree::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // returns the old value if key was already in the map
+    // (a key which is not comparable, e.g. NaN, is not inserted)
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.tree.insert_or_get(MapEntry { key, value }) {
+            Some((entry, Some(new_entry))) => Some(mem::replace(&mut entry.value, new_entry.value)),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.tree
+            .get_by(|entry| entry.key.partial_cmp(key))
+            .map(|entry| &entry.value)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.tree
+            .get_mut_by(|entry| entry.key.partial_cmp(key))
+            .map(|entry| &mut entry.value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
+        self.tree
+            .remove_by(|entry| entry.key.partial_cmp(key))
+            .map(|entry| (entry.key, entry.value))
+    }
+
+    // a key which is not comparable (e.g. NaN) has no entry, whether or not
+    // the map is empty
+    pub fn entry(&mut self, key: K) -> Result<Entry<'_, K, V>, IncomparableError<K>> {
+        if key.partial_cmp(&key) != Some(Ordering::Equal) {
+            Err(IncomparableError { data: key })
+        } else if self.contains_key(&key) {
+            Ok(Entry::Occupied(OccupiedEntry { map: self, key }))
+        } else {
+            Ok(Entry::Vacant(VacantEntry { map: self, key }))
+        }
+    }
+
+    // iterates in ascending order of keys
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.tree.iter(),
+        }
+    }
+
+    // iterates in ascending order over keys within the bounds
+    pub fn range(&self, range: impl RangeBounds<K>) -> Iter<'_, K, V> {
+        let before = |entry: &MapEntry<K, V>| match range.start_bound() {
+            Bound::Included(start) => entry.key.partial_cmp(start) == Some(Ordering::Less),
+            Bound::Excluded(start) => entry.key.partial_cmp(start).is_some_and(|o| o.is_le()),
+            Bound::Unbounded => false,
+        };
+        let until = |entry: &MapEntry<K, V>| match range.end_bound() {
+            Bound::Included(end) => entry.key.partial_cmp(end).is_some_and(|o| o.is_le()),
+            Bound::Excluded(end) => entry.key.partial_cmp(end) == Some(Ordering::Less),
+            Bound::Unbounded => true,
+        };
+        Iter {
+            inner: self.tree.range_by(before, until),
+        }
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+pub enum Entry<'a, K: PartialOrd + Debug, V> {
+    Vacant(VacantEntry<'a, K, V>),
+    Occupied(OccupiedEntry<'a, K, V>),
+}
+
+pub struct VacantEntry<'a, K: PartialOrd + Debug, V> {
+    map: &'a mut RBTreeMap<K, V>,
+    key: K,
+}
+
+pub struct OccupiedEntry<'a, K: PartialOrd + Debug, V> {
+    map: &'a mut RBTreeMap<K, V>,
+    key: K,
+}
+
+impl<'a, K: PartialOrd + Debug, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Vacant(entry) => entry.key(),
+            Entry::Occupied(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        match self {
+            Entry::Vacant(entry) => entry.insert(default()),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K: PartialOrd + Debug, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        let (entry, _) = self
+            .map
+            .tree
+            .insert_or_get(MapEntry {
+                key: self.key,
+                value,
+            })
+            .expect("a key comparable with itself is comparable with the map's keys");
+        &mut entry.value
+    }
+}
+
+impl<'a, K: PartialOrd + Debug, V> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn get(&self) -> &V {
+        self.map.get(&self.key).unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.map.get_mut(&self.key).unwrap()
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.map.get_mut(&self.key).unwrap()
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        self.map.remove_entry(&self.key).unwrap()
+    }
+}
+
+pub struct Iter<'a, K: PartialOrd + Debug, V> {
+    inner: tree::Iter<'a, MapEntry<K, V>>,
+}
+
+impl<'a, K: PartialOrd + Debug, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        self.inner.next().map(|entry| (&entry.key, &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K: PartialOrd + Debug, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+        self.inner
+            .next_back()
+            .map(|entry| (&entry.key, &entry.value))
+    }
+}
+
+impl<K: PartialOrd + Debug, V> ExactSizeIterator for Iter<'_, K, V> {}
+impl<K: PartialOrd + Debug, V> FusedIterator for Iter<'_, K, V> {}
+
+impl<'a, K: PartialOrd + Debug, V> IntoIterator for &'a RBTreeMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        map::{Entry, RBTreeMap},
+        model_test::test_rng,
+    };
+    use rand::prelude::*;
+    use std::collections::BTreeMap;
+    #[test]
+    fn insert_get_remove_test() {
+        let mut map = RBTreeMap::<u64, u64>::new();
+        let mut btree = BTreeMap::<u64, u64>::new();
+        let mut rng = test_rng();
+        for _ in 0..10000 {
+            let (k, v) = (rng.gen_range(0..1000), rng.gen());
+            assert_eq!(map.insert(k, v), btree.insert(k, v));
+            if rng.gen_bool(0.3) {
+                let k = rng.gen_range(0..1000);
+                assert_eq!(map.remove(&k), btree.remove(&k));
+            }
+            if rng.gen_bool(0.3) {
+                let k = rng.gen_range(0..1000);
+                if let Some(v) = map.get_mut(&k) {
+                    *v += 1;
+                }
+                if let Some(v) = btree.get_mut(&k) {
+                    *v += 1;
+                }
+            }
+        }
+
+        assert_eq!(map.len(), btree.len());
+        for k in 0..1000 {
+            assert_eq!(map.get(&k), btree.get(&k));
+        }
+        assert!(map.iter().eq(btree.iter()));
+        assert!(map.iter().rev().eq(btree.iter().rev()));
+        assert!(map.keys().eq(btree.keys()));
+        for _ in 0..100 {
+            let (a, b) = (rng.gen_range(0..1000), rng.gen_range(0..1000));
+            let (a, b) = (a.min(b), a.max(b));
+            assert!(map.range(a..b).eq(btree.range(a..b)));
+            assert!(map.range(a..=b).rev().eq(btree.range(a..=b).rev()));
+            assert!(map.range(..b).eq(btree.range(..b)));
+        }
+    }
+    #[test]
+    fn entry_test() {
+        let mut map = RBTreeMap::<String, usize>::new();
+        let text = "the quick brown fox jumps over the lazy dog the end";
+        for word in text.split(' ') {
+            *map.entry(word.to_string()).unwrap().or_insert(0) += 1;
+        }
+        assert_eq!(map.get(&"the".to_string()), Some(&3));
+        assert_eq!(map.get(&"fox".to_string()), Some(&1));
+        assert_eq!(map.len(), 9);
+
+        map.entry("fox".to_string())
+            .unwrap()
+            .and_modify(|n| *n += 10);
+        assert_eq!(map.get(&"fox".to_string()), Some(&11));
+
+        if let Ok(Entry::Occupied(entry)) = map.entry("dog".to_string()) {
+            assert_eq!(entry.remove(), 1);
+        } else {
+            panic!("dog must be occupied");
+        }
+        assert!(!map.contains_key(&"dog".to_string()));
+        assert_eq!(map.len(), 8);
+
+        // NaN has no entry in an empty map nor in a filled one
+        let mut floats = RBTreeMap::<f64, u32>::new();
+        assert!(floats.entry(f64::NAN).is_err());
+        *floats.entry(1.0).unwrap().or_default() += 1;
+        assert!(floats.entry(f64::NAN).is_err());
+        assert_eq!(floats.len(), 1);
+    }
+}
@@ -0,0 +1,267 @@
+// property tests of every `OrderedSet` against `BTreeMap` (value -> count)
+// as the reference model
+// every case is generated from its number, so a failure is replayed by
+//   RB_TREE_CASE=<case> cargo test model_test
+// and more cases are run by RB_TREE_CASES=<n> (500 by default).
+// a failing sequence is shrunk before it is reported.
+// the other randomized tests take their rng from `test_rng`, which is
+// seeded by RB_TREE_SEED.
+use crate::{avl::AvlTree, ordered_set::OrderedSet, treap::Treap, tree::RBTree};
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+use std::{collections::BTreeMap, thread};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Insert(u32),
+    Delete(u32),
+    Find(u32),
+}
+
+impl Op {
+    fn value(self) -> u32 {
+        match self {
+            Op::Insert(n) | Op::Delete(n) | Op::Find(n) => n,
+        }
+    }
+
+    fn with_value(self, n: u32) -> Op {
+        match self {
+            Op::Insert(_) => Op::Insert(n),
+            Op::Delete(_) => Op::Delete(n),
+            Op::Find(_) => Op::Find(n),
+        }
+    }
+}
+
+fn gen_ops(seed: u64) -> Vec<Op> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    // small ranges give many duplicates, large ones deep trees
+    let range = [4, 32, 256, 65536][rng.gen_range(0..4)];
+    let insert_ratio = rng.gen_range(0.3..0.8);
+    (0..rng.gen_range(1..300))
+        .map(|_| {
+            let n = rng.gen_range(0..range);
+            match rng.gen::<f64>() {
+                p if p < insert_ratio => Op::Insert(n),
+                p if p < (1.0 + insert_ratio) / 2.0 => Op::Delete(n),
+                _ => Op::Find(n),
+            }
+        })
+        .collect()
+}
+
+// replays ops on a tree and on the model, comparing them after every step
+fn run<S: OrderedSet<u32>>(ops: &[Op]) -> Result<(), String> {
+    let mut tree = S::new();
+    let mut model = BTreeMap::<u32, usize>::new();
+    for (i, &op) in ops.iter().enumerate() {
+        let (found, expected) = match op {
+            Op::Insert(n) => {
+                *model.entry(n).or_default() += 1;
+                (tree.insert(n), true)
+            }
+            Op::Delete(n) => {
+                let expected = match model.get_mut(&n) {
+                    Some(cnt) if *cnt > 1 => {
+                        *cnt -= 1;
+                        true
+                    }
+                    Some(_) => model.remove(&n).is_some(),
+                    None => false,
+                };
+                (tree.delete(&n), expected)
+            }
+            Op::Find(n) => (tree.find(&n), model.contains_key(&n)),
+        };
+        if found != expected {
+            return Err(format!(
+                "step {i} {op:?}: returned {found}, expected {expected}"
+            ));
+        }
+        if let Err(violation) = tree.check_invariants() {
+            return Err(format!("step {i} {op:?}: {violation}"));
+        }
+        let len: usize = model.values().sum();
+        if tree.len() != len {
+            return Err(format!(
+                "step {i} {op:?}: len {}, expected {len}",
+                tree.len()
+            ));
+        }
+        let values = model
+            .iter()
+            .flat_map(|(&n, &cnt)| std::iter::repeat_n(n, cnt));
+        if !tree.iter().copied().eq(values) {
+            return Err(format!("step {i} {op:?}: values differ from the model"));
+        }
+    }
+    Ok(())
+}
+
+// smallest sequence found which still fails:
+// drops chunks of ops (halving the chunk size down to 1), then lowers values
+fn shrink(mut ops: Vec<Op>, fails: impl Fn(&[Op]) -> bool) -> Vec<Op> {
+    loop {
+        let mut shrunk = false;
+
+        let mut chunk = ops.len().div_ceil(2);
+        while chunk > 0 {
+            let mut start = 0;
+            while start < ops.len() {
+                let mut candidate = ops.clone();
+                candidate.drain(start..(start + chunk).min(ops.len()));
+                if fails(&candidate) {
+                    ops = candidate;
+                    shrunk = true;
+                } else {
+                    start += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+
+        // every op on the same value together first, then op by op
+        let mut values: Vec<u32> = ops.iter().map(|op| op.value()).collect();
+        values.sort();
+        values.dedup();
+        for n in values {
+            for smaller in [0, n / 2, n.saturating_sub(1)] {
+                if smaller >= n || ops.iter().any(|op| op.value() == smaller) {
+                    continue;
+                }
+                let candidate: Vec<Op> = ops
+                    .iter()
+                    .map(|&op| {
+                        if op.value() == n {
+                            op.with_value(smaller)
+                        } else {
+                            op
+                        }
+                    })
+                    .collect();
+                if fails(&candidate) {
+                    ops = candidate;
+                    shrunk = true;
+                    break;
+                }
+            }
+        }
+        for i in 0..ops.len() {
+            let n = ops[i].value();
+            for smaller in [0, n / 2, n.saturating_sub(1)] {
+                if smaller >= n {
+                    continue;
+                }
+                let mut candidate = ops.clone();
+                candidate[i] = ops[i].with_value(smaller);
+                if fails(&candidate) {
+                    ops = candidate;
+                    shrunk = true;
+                    break;
+                }
+            }
+        }
+
+        if !shrunk {
+            return ops;
+        }
+    }
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    std::env::var(name).ok().map(|v| {
+        v.parse()
+            .unwrap_or_else(|_| panic!("{name} must be a number, not {v:?}"))
+    })
+}
+
+// rng of a randomized test, seeded by RB_TREE_SEED or else by a fixed
+// seed, so a run is only different from the last one when asked to be
+pub(crate) struct TestRng {
+    rng: StdRng,
+    seed: u64,
+}
+
+pub(crate) fn test_rng() -> TestRng {
+    let seed = env_u64("RB_TREE_SEED").unwrap_or(0);
+    TestRng {
+        rng: StdRng::seed_from_u64(seed),
+        seed,
+    }
+}
+
+impl Drop for TestRng {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            eprintln!("replay with RB_TREE_SEED={}", self.seed);
+        }
+    }
+}
+
+impl RngCore for TestRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+fn model_test<S: OrderedSet<u32>>() {
+    let cases = match env_u64("RB_TREE_CASE") {
+        Some(case) => case..case + 1,
+        None => 0..env_u64("RB_TREE_CASES").unwrap_or(500),
+    };
+    for case in cases {
+        let ops = gen_ops(case);
+        if let Err(err) = run::<S>(&ops) {
+            let minimal = shrink(ops, |ops| run::<S>(ops).is_err());
+            panic!(
+                "case {case} failed: {err}\nminimal ops: {minimal:?}\nminimal failure: {}",
+                run::<S>(&minimal).unwrap_err()
+            );
+        }
+    }
+}
+
+#[test]
+fn rbtree_model_test() {
+    model_test::<RBTree<u32>>();
+}
+
+#[test]
+fn avl_model_test() {
+    model_test::<AvlTree<u32>>();
+}
+
+#[test]
+fn treap_model_test() {
+    model_test::<Treap<u32>>();
+}
+
+#[test]
+fn shrink_test() {
+    // fails once some value >= 10 is inserted and then deleted
+    let fails = |ops: &[Op]| {
+        ops.iter().enumerate().any(|(i, op)| match op {
+            Op::Insert(n) if *n >= 10 => ops[i..].contains(&Op::Delete(*n)),
+            _ => false,
+        })
+    };
+    let mut ops = gen_ops(7);
+    ops.extend([Op::Insert(500), Op::Find(3), Op::Delete(500)]);
+    ops.extend(gen_ops(8));
+    assert!(fails(&ops));
+
+    let minimal = shrink(ops, fails);
+    assert_eq!(minimal, vec![Op::Insert(10), Op::Delete(10)]);
+}
@@ -0,0 +1,282 @@
+// operations shared by the balanced trees, so a workload can be run on
+// each of them to compare balancing strategies
+use crate::tree::{self, RBTree};
+use std::{cmp::Ordering, fmt::Debug};
+
+// sorted multiset: equal values are kept once with a count
+pub trait OrderedSet<T> {
+    type Iter<'a>: Iterator<Item = &'a T>
+    where
+        Self: 'a,
+        T: 'a;
+
+    fn new() -> Self;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    // false if data is not comparable with the values in the set
+    fn insert(&mut self, data: T) -> bool;
+    // removes one copy of data
+    fn delete(&mut self, data: &T) -> bool;
+    fn find(&self, data: &T) -> bool;
+    fn min(&self) -> Option<&T>;
+    fn max(&self) -> Option<&T>;
+    // ascending order, each value as many times as it was inserted
+    fn iter(&self) -> Self::Iter<'_>;
+    // nodes on the longest path from the root
+    fn height(&self) -> u32;
+    // the first broken invariant of the tree, if any
+    fn check_invariants(&self) -> Result<(), String>;
+}
+
+impl<T: PartialOrd + Debug> OrderedSet<T> for RBTree<T> {
+    type Iter<'a>
+        = tree::Iter<'a, T>
+    where
+        T: 'a;
+
+    fn new() -> Self {
+        RBTree::new()
+    }
+    fn len(&self) -> usize {
+        RBTree::len(self)
+    }
+    fn insert(&mut self, data: T) -> bool {
+        RBTree::insert(self, data)
+    }
+    fn delete(&mut self, data: &T) -> bool {
+        RBTree::delete(self, data)
+    }
+    fn find(&self, data: &T) -> bool {
+        RBTree::find(self, data)
+    }
+    fn min(&self) -> Option<&T> {
+        RBTree::min(self)
+    }
+    fn max(&self) -> Option<&T> {
+        RBTree::max(self)
+    }
+    fn iter(&self) -> tree::Iter<'_, T> {
+        RBTree::iter(self)
+    }
+    fn height(&self) -> u32 {
+        self.check().3
+    }
+    fn check_invariants(&self) -> Result<(), String> {
+        match self.validate() {
+            Ok(_) => Ok(()),
+            Err(violations) => Err(violations[0].to_string()),
+        }
+    }
+}
+
+// node of the pointer-based trees (AVL, treap); `balance` is what each of
+// them keeps to stay balanced (height, priority)
+pub(crate) struct BoxNode<T, B> {
+    pub data: T,
+    pub cnt: usize,
+    pub balance: B,
+    pub lchild: BoxLink<T, B>,
+    pub rchild: BoxLink<T, B>,
+}
+
+pub(crate) type BoxLink<T, B> = Option<Box<BoxNode<T, B>>>;
+
+impl<T, B> BoxNode<T, B> {
+    pub fn new(data: T, balance: B) -> Box<BoxNode<T, B>> {
+        Box::new(BoxNode {
+            data,
+            cnt: 1,
+            balance,
+            lchild: None,
+            rchild: None,
+        })
+    }
+}
+
+// whether data can be ordered at all (false for NaN); an empty tree has
+// nothing else to compare a new value with
+pub(crate) fn comparable<T: PartialOrd>(data: &T) -> bool {
+    data.partial_cmp(data) == Some(Ordering::Equal)
+}
+
+pub(crate) fn find_in<T: PartialOrd, B>(mut link: &BoxLink<T, B>, data: &T) -> bool {
+    while let Some(node) = link {
+        link = match data.partial_cmp(&node.data) {
+            Some(Ordering::Less) => &node.lchild,
+            Some(Ordering::Greater) => &node.rchild,
+            Some(Ordering::Equal) => return true,
+            None => return false,
+        };
+    }
+    false
+}
+
+pub(crate) fn min_in<T, B>(link: &BoxLink<T, B>) -> Option<&T> {
+    let mut node = link.as_ref()?;
+    while let Some(lchild) = &node.lchild {
+        node = lchild;
+    }
+    Some(&node.data)
+}
+
+pub(crate) fn max_in<T, B>(link: &BoxLink<T, B>) -> Option<&T> {
+    let mut node = link.as_ref()?;
+    while let Some(rchild) = &node.rchild {
+        node = rchild;
+    }
+    Some(&node.data)
+}
+
+//     y          x
+//    / \        / \
+//   x   c  ->  a   y
+//  / \            / \
+// a   b          b   c
+pub(crate) fn rotate_right<T, B>(mut node: Box<BoxNode<T, B>>) -> Box<BoxNode<T, B>> {
+    let mut lchild = node
+        .lchild
+        .take()
+        .expect("rotate_right without a left child");
+    node.lchild = lchild.rchild.take();
+    lchild.rchild = Some(node);
+    lchild
+}
+
+pub(crate) fn rotate_left<T, B>(mut node: Box<BoxNode<T, B>>) -> Box<BoxNode<T, B>> {
+    let mut rchild = node
+        .rchild
+        .take()
+        .expect("rotate_left without a right child");
+    node.rchild = rchild.lchild.take();
+    rchild.lchild = Some(node);
+    rchild
+}
+
+// in-order walk over the pointer-based trees
+pub struct BoxIter<'a, T, B> {
+    stack: Vec<&'a BoxNode<T, B>>,
+    used: usize, // copies of the top of the stack already yielded
+}
+
+impl<'a, T, B> BoxIter<'a, T, B> {
+    pub(crate) fn new(root: &'a BoxLink<T, B>) -> BoxIter<'a, T, B> {
+        let mut iter = BoxIter {
+            stack: vec![],
+            used: 0,
+        };
+        iter.push_left(root);
+        iter
+    }
+
+    fn push_left(&mut self, mut link: &'a BoxLink<T, B>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = &node.lchild;
+        }
+    }
+}
+
+impl<'a, T, B> Iterator for BoxIter<'a, T, B> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = *self.stack.last()?;
+        self.used += 1;
+        if self.used == node.cnt {
+            self.used = 0;
+            self.stack.pop();
+            self.push_left(&node.rchild);
+        }
+        Some(&node.data)
+    }
+}
+
+// checks BST order and counts of the subtree with every value in (lo, hi);
+// returns the number of values stored
+pub(crate) fn check_order<T: PartialOrd + Debug, B>(
+    link: &BoxLink<T, B>,
+    lo: Option<&T>,
+    hi: Option<&T>,
+) -> Result<usize, String> {
+    let node = match link {
+        Some(node) => node,
+        None => return Ok(0),
+    };
+    if node.cnt == 0 {
+        return Err(format!("{:?} is stored with count 0", node.data));
+    }
+    if lo.is_some_and(|lo| !matches!(lo.partial_cmp(&node.data), Some(Ordering::Less)))
+        || hi.is_some_and(|hi| !matches!(node.data.partial_cmp(hi), Some(Ordering::Less)))
+    {
+        return Err(format!("{:?} is out of order", node.data));
+    }
+    let l = check_order(&node.lchild, lo, Some(&node.data))?;
+    let r = check_order(&node.rchild, Some(&node.data), hi)?;
+    Ok(l + node.cnt + r)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{avl::AvlTree, ordered_set::OrderedSet, treap::Treap, tree::RBTree};
+    use rand::prelude::*;
+
+    // the same operations on every implementation
+    fn ordered_set_test<S: OrderedSet<u64>>() {
+        let mut rng = StdRng::seed_from_u64(20);
+        let mut set = S::new();
+        let mut nums: Vec<u64> = vec![];
+        assert!(set.is_empty());
+        assert_eq!(set.min(), None);
+        for _ in 0..3000 {
+            let n = rng.gen_range(0..1000);
+            assert!(set.insert(n));
+            nums.push(n);
+            assert_eq!(set.check_invariants(), Ok(()));
+        }
+        nums.sort();
+        assert_eq!(set.len(), nums.len());
+        assert_eq!(set.min(), nums.first());
+        assert_eq!(set.max(), nums.last());
+        assert!(set.iter().eq(nums.iter()));
+        // 1000 distinct values: at least log2(1000), and a treap is a few
+        // times that at worst
+        assert!((10..=30).contains(&set.height()));
+        assert!(!set.find(&1000));
+
+        nums.shuffle(&mut rng);
+        for (i, n) in nums.iter().enumerate() {
+            assert!(set.delete(n));
+            assert_eq!(set.check_invariants(), Ok(()));
+            assert_eq!(set.len(), nums.len() - i - 1);
+        }
+        assert!(set.is_empty());
+        assert!(!set.delete(&0));
+    }
+
+    // NaN is refused by an empty set too
+    fn nan_test<S: OrderedSet<f64>>() {
+        let mut set = S::new();
+        assert!(!set.insert(f64::NAN));
+        assert!(set.is_empty());
+        assert!(set.insert(1.0));
+        assert!(!set.insert(f64::NAN));
+        assert_eq!(set.len(), 1);
+    }
+    #[test]
+    fn rbtree_test() {
+        ordered_set_test::<RBTree<u64>>();
+        nan_test::<RBTree<f64>>();
+    }
+    #[test]
+    fn avl_test() {
+        ordered_set_test::<AvlTree<u64>>();
+        nan_test::<AvlTree<f64>>();
+    }
+    #[test]
+    fn treap_test() {
+        ordered_set_test::<Treap<u64>>();
+        nan_test::<Treap<f64>>();
+    }
+}
@@ -0,0 +1,613 @@
+use crate::{ordered_set, tree::Color};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+    sync::Arc,
+};
+
+// Persistent (immutable) red-black tree.
+// `insert`/`delete` copy only the path from root to the changed node and share
+// every other subtree with the previous version, so each version is a cheap
+// snapshot which never changes.
+// Balancing follows Okasaki's insertion and Kahrs' deletion.
+
+struct PNode<T> {
+    data: T,
+    cnt: usize,
+    color: Color,
+    lchild: Link<T>,
+    rchild: Link<T>,
+}
+
+type Link<T> = Option<Arc<PNode<T>>>;
+
+fn new_node<T>(color: Color, lchild: Link<T>, data: T, cnt: usize, rchild: Link<T>) -> Link<T> {
+    Some(Arc::new(PNode {
+        data,
+        cnt,
+        color,
+        lchild,
+        rchild,
+    }))
+}
+
+fn is_red<T>(link: &Link<T>) -> bool {
+    matches!(link, Some(node) if node.color == Color::Red)
+}
+
+fn is_black_node<T>(link: &Link<T>) -> bool {
+    matches!(link, Some(node) if node.color == Color::Black)
+}
+
+// copy of the node in another color
+fn paint<T: Clone>(link: &Link<T>, color: Color) -> Link<T> {
+    match link {
+        Some(node) if node.color != color => new_node(
+            color,
+            node.lchild.clone(),
+            node.data.clone(),
+            node.cnt,
+            node.rchild.clone(),
+        ),
+        _ => link.clone(),
+    }
+}
+
+// black node with (l, data, r), rotated if a red child has a red child
+//       z            x            x          z
+//     y   d        a   y        a   z      x   d
+//   x   c   or   b       z  or     y  d  a   y     =>     y
+//  a b            c d       b   c             b  c       x   z
+//                                                       a b c d
+fn balance<T: Clone>(l: Link<T>, data: T, cnt: usize, r: Link<T>) -> Link<T> {
+    if is_red(&l) && is_red(&r) {
+        return new_node(
+            Color::Red,
+            paint(&l, Color::Black),
+            data,
+            cnt,
+            paint(&r, Color::Black),
+        );
+    }
+    if let Some(ln) = l.as_ref().filter(|node| node.color == Color::Red) {
+        if is_red(&ln.lchild) {
+            return new_node(
+                Color::Red,
+                paint(&ln.lchild, Color::Black),
+                ln.data.clone(),
+                ln.cnt,
+                new_node(Color::Black, ln.rchild.clone(), data, cnt, r),
+            );
+        }
+        if let Some(lr) = ln.rchild.as_ref().filter(|node| node.color == Color::Red) {
+            return new_node(
+                Color::Red,
+                new_node(
+                    Color::Black,
+                    ln.lchild.clone(),
+                    ln.data.clone(),
+                    ln.cnt,
+                    lr.lchild.clone(),
+                ),
+                lr.data.clone(),
+                lr.cnt,
+                new_node(Color::Black, lr.rchild.clone(), data, cnt, r),
+            );
+        }
+    }
+    if let Some(rn) = r.as_ref().filter(|node| node.color == Color::Red) {
+        if is_red(&rn.rchild) {
+            return new_node(
+                Color::Red,
+                new_node(Color::Black, l, data, cnt, rn.lchild.clone()),
+                rn.data.clone(),
+                rn.cnt,
+                paint(&rn.rchild, Color::Black),
+            );
+        }
+        if let Some(rl) = rn.lchild.as_ref().filter(|node| node.color == Color::Red) {
+            return new_node(
+                Color::Red,
+                new_node(Color::Black, l, data, cnt, rl.lchild.clone()),
+                rl.data.clone(),
+                rl.cnt,
+                new_node(
+                    Color::Black,
+                    rl.rchild.clone(),
+                    rn.data.clone(),
+                    rn.cnt,
+                    rn.rchild.clone(),
+                ),
+            );
+        }
+    }
+    new_node(Color::Black, l, data, cnt, r)
+}
+
+// l lost one black height by deletion
+fn balance_left<T: Clone>(l: Link<T>, data: T, cnt: usize, r: Link<T>) -> Link<T> {
+    if is_red(&l) {
+        return new_node(Color::Red, paint(&l, Color::Black), data, cnt, r);
+    }
+    match &r {
+        Some(rn) if rn.color == Color::Black => balance(l, data, cnt, paint(&r, Color::Red)),
+        Some(rn) => match &rn.lchild {
+            Some(rl) if rl.color == Color::Black => new_node(
+                Color::Red,
+                new_node(Color::Black, l, data, cnt, rl.lchild.clone()),
+                rl.data.clone(),
+                rl.cnt,
+                balance(
+                    rl.rchild.clone(),
+                    rn.data.clone(),
+                    rn.cnt,
+                    paint(&rn.rchild, Color::Red),
+                ),
+            ),
+            _ => unreachable!("red node must have black children"),
+        },
+        None => unreachable!("sibling of a shortened subtree must exist"),
+    }
+}
+
+// r lost one black height by deletion
+fn balance_right<T: Clone>(l: Link<T>, data: T, cnt: usize, r: Link<T>) -> Link<T> {
+    if is_red(&r) {
+        return new_node(Color::Red, l, data, cnt, paint(&r, Color::Black));
+    }
+    match &l {
+        Some(ln) if ln.color == Color::Black => balance(paint(&l, Color::Red), data, cnt, r),
+        Some(ln) => match &ln.rchild {
+            Some(lr) if lr.color == Color::Black => new_node(
+                Color::Red,
+                balance(
+                    paint(&ln.lchild, Color::Red),
+                    ln.data.clone(),
+                    ln.cnt,
+                    lr.lchild.clone(),
+                ),
+                lr.data.clone(),
+                lr.cnt,
+                new_node(Color::Black, lr.rchild.clone(), data, cnt, r),
+            ),
+            _ => unreachable!("red node must have black children"),
+        },
+        None => unreachable!("sibling of a shortened subtree must exist"),
+    }
+}
+
+// joins two subtrees of a deleted node (every value of l < every value of r)
+fn fuse<T: Clone>(l: &Link<T>, r: &Link<T>) -> Link<T> {
+    let (ln, rn) = match (l, r) {
+        (None, _) => return r.clone(),
+        (_, None) => return l.clone(),
+        (Some(ln), Some(rn)) => (ln, rn),
+    };
+    match (ln.color, rn.color) {
+        (Color::Red, Color::Red) => {
+            let middle = fuse(&ln.rchild, &rn.lchild);
+            match &middle {
+                Some(mn) if mn.color == Color::Red => new_node(
+                    Color::Red,
+                    new_node(
+                        Color::Red,
+                        ln.lchild.clone(),
+                        ln.data.clone(),
+                        ln.cnt,
+                        mn.lchild.clone(),
+                    ),
+                    mn.data.clone(),
+                    mn.cnt,
+                    new_node(
+                        Color::Red,
+                        mn.rchild.clone(),
+                        rn.data.clone(),
+                        rn.cnt,
+                        rn.rchild.clone(),
+                    ),
+                ),
+                _ => new_node(
+                    Color::Red,
+                    ln.lchild.clone(),
+                    ln.data.clone(),
+                    ln.cnt,
+                    new_node(
+                        Color::Red,
+                        middle.clone(),
+                        rn.data.clone(),
+                        rn.cnt,
+                        rn.rchild.clone(),
+                    ),
+                ),
+            }
+        }
+        (Color::Black, Color::Black) => {
+            let middle = fuse(&ln.rchild, &rn.lchild);
+            match &middle {
+                Some(mn) if mn.color == Color::Red => new_node(
+                    Color::Red,
+                    new_node(
+                        Color::Black,
+                        ln.lchild.clone(),
+                        ln.data.clone(),
+                        ln.cnt,
+                        mn.lchild.clone(),
+                    ),
+                    mn.data.clone(),
+                    mn.cnt,
+                    new_node(
+                        Color::Black,
+                        mn.rchild.clone(),
+                        rn.data.clone(),
+                        rn.cnt,
+                        rn.rchild.clone(),
+                    ),
+                ),
+                _ => balance_left(
+                    ln.lchild.clone(),
+                    ln.data.clone(),
+                    ln.cnt,
+                    new_node(
+                        Color::Black,
+                        middle.clone(),
+                        rn.data.clone(),
+                        rn.cnt,
+                        rn.rchild.clone(),
+                    ),
+                ),
+            }
+        }
+        (_, Color::Red) => new_node(
+            Color::Red,
+            fuse(l, &rn.lchild),
+            rn.data.clone(),
+            rn.cnt,
+            rn.rchild.clone(),
+        ),
+        (Color::Red, _) => new_node(
+            Color::Red,
+            ln.lchild.clone(),
+            ln.data.clone(),
+            ln.cnt,
+            fuse(&ln.rchild, r),
+        ),
+    }
+}
+
+pub struct PersistentRBTree<T> {
+    root: Link<T>,
+    cnt: usize,
+}
+
+// a snapshot: shares every node with the original
+impl<T> Clone for PersistentRBTree<T> {
+    fn clone(&self) -> Self {
+        PersistentRBTree {
+            root: self.root.clone(),
+            cnt: self.cnt,
+        }
+    }
+}
+
+impl<T> PersistentRBTree<T> {
+    // iterates in ascending order, yielding each value `cnt` times
+    pub fn iter(&self) -> PersistentIter<'_, T> {
+        let mut iter = PersistentIter {
+            stack: vec![],
+            used: 0,
+        };
+        iter.push_left(&self.root);
+        iter
+    }
+}
+
+impl<T: Debug> Debug for PersistentRBTree<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialOrd + Clone + Debug> Default for PersistentRBTree<T> {
+    fn default() -> Self {
+        PersistentRBTree::new()
+    }
+}
+
+impl<T: PartialOrd + Clone + Debug> PersistentRBTree<T> {
+    pub fn new() -> PersistentRBTree<T> {
+        PersistentRBTree { root: None, cnt: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cnt
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cnt == 0
+    }
+
+    pub fn find(&self, data: &T) -> bool {
+        self.find_node(data).is_some()
+    }
+
+    // new version with data added
+    // (the same version if data is not comparable, e.g. NaN)
+    pub fn insert(&self, data: T) -> PersistentRBTree<T> {
+        match Self::ins(&self.root, data) {
+            Some(root) => PersistentRBTree {
+                root: paint(&root, Color::Black),
+                cnt: self.cnt + 1,
+            },
+            None => self.clone(),
+        }
+    }
+
+    // new version with one copy of data removed
+    // (the same version if data is not in the tree)
+    pub fn delete(&self, data: &T) -> PersistentRBTree<T> {
+        let root = match self.find_node(data) {
+            Some(node) if node.cnt > 1 => Self::decrease(&self.root, data),
+            Some(_) => paint(&Self::del(&self.root, data), Color::Black),
+            None => return self.clone(),
+        };
+        PersistentRBTree {
+            root,
+            cnt: self.cnt - 1,
+        }
+    }
+
+    fn find_node(&self, data: &T) -> Option<&PNode<T>> {
+        let mut c = &self.root;
+        while let Some(current) = c {
+            match data.partial_cmp(&current.data)? {
+                Ordering::Less => c = &current.lchild,
+                Ordering::Greater => c = &current.rchild,
+                Ordering::Equal => return Some(current),
+            }
+        }
+        None
+    }
+
+    // None if data is not comparable
+    fn ins(link: &Link<T>, data: T) -> Option<Link<T>> {
+        let node = match link {
+            Some(node) => node,
+            None if !ordered_set::comparable(&data) => return None,
+            None => return Some(new_node(Color::Red, None, data, 1, None)),
+        };
+        let copy = |lchild, rchild| {
+            if node.color == Color::Black {
+                balance(lchild, node.data.clone(), node.cnt, rchild)
+            } else {
+                new_node(Color::Red, lchild, node.data.clone(), node.cnt, rchild)
+            }
+        };
+        Some(match data.partial_cmp(&node.data)? {
+            Ordering::Less => copy(Self::ins(&node.lchild, data)?, node.rchild.clone()),
+            Ordering::Greater => copy(node.lchild.clone(), Self::ins(&node.rchild, data)?),
+            Ordering::Equal => new_node(
+                node.color,
+                node.lchild.clone(),
+                node.data.clone(),
+                node.cnt + 1,
+                node.rchild.clone(),
+            ),
+        })
+    }
+
+    // data must be in the tree with cnt > 1
+    fn decrease(link: &Link<T>, data: &T) -> Link<T> {
+        let node = link.as_ref()?;
+        let (lchild, cnt, rchild) = match data.partial_cmp(&node.data) {
+            Some(Ordering::Less) => (
+                Self::decrease(&node.lchild, data),
+                node.cnt,
+                node.rchild.clone(),
+            ),
+            Some(Ordering::Greater) => (
+                node.lchild.clone(),
+                node.cnt,
+                Self::decrease(&node.rchild, data),
+            ),
+            _ => (node.lchild.clone(), node.cnt - 1, node.rchild.clone()),
+        };
+        new_node(node.color, lchild, node.data.clone(), cnt, rchild)
+    }
+
+    // data must be in the tree; the result may be a red root with a red child
+    fn del(link: &Link<T>, data: &T) -> Link<T> {
+        let node = link.as_ref()?;
+        match data.partial_cmp(&node.data) {
+            Some(Ordering::Less) => {
+                let lchild = Self::del(&node.lchild, data);
+                if is_black_node(&node.lchild) {
+                    balance_left(lchild, node.data.clone(), node.cnt, node.rchild.clone())
+                } else {
+                    new_node(
+                        Color::Red,
+                        lchild,
+                        node.data.clone(),
+                        node.cnt,
+                        node.rchild.clone(),
+                    )
+                }
+            }
+            Some(Ordering::Greater) => {
+                let rchild = Self::del(&node.rchild, data);
+                if is_black_node(&node.rchild) {
+                    balance_right(node.lchild.clone(), node.data.clone(), node.cnt, rchild)
+                } else {
+                    new_node(
+                        Color::Red,
+                        node.lchild.clone(),
+                        node.data.clone(),
+                        node.cnt,
+                        rchild,
+                    )
+                }
+            }
+            _ => fuse(&node.lchild, &node.rchild),
+        }
+    }
+
+    fn _check(
+        link: &Link<T>,
+        range: (Option<&T>, Option<&T>),
+        depth: u32,
+        min_depth: &mut u32,
+        max_depth: &mut u32,
+    ) -> Option<u32> {
+        let node = match link {
+            Some(node) => node,
+            None => return Some(0),
+        };
+        let (low, high) = range;
+        if low.is_some_and(|low| node.data.partial_cmp(low) != Some(Ordering::Greater))
+            || high.is_some_and(|high| node.data.partial_cmp(high) != Some(Ordering::Less))
+        {
+            return None;
+        }
+        if node.color == Color::Red && (is_red(&node.lchild) || is_red(&node.rchild)) {
+            return None;
+        }
+        if node.lchild.is_none() || node.rchild.is_none() {
+            *min_depth = (*min_depth).min(depth);
+            *max_depth = (*max_depth).max(depth);
+        }
+        let lblacks = Self::_check(
+            &node.lchild,
+            (low, Some(&node.data)),
+            depth + 1,
+            min_depth,
+            max_depth,
+        )?;
+        let rblacks = Self::_check(
+            &node.rchild,
+            (Some(&node.data), high),
+            depth + 1,
+            min_depth,
+            max_depth,
+        )?;
+        if lblacks != rblacks {
+            return None;
+        }
+        Some(lblacks + if node.color == Color::Black { 1 } else { 0 })
+    }
+
+    // returns (rb-tree or not, black cnt, min_depth, max depth) like `RBTree::check`
+    pub fn check(&self) -> (bool, u32, u32, u32) {
+        let mut max_depth = 0;
+        let mut min_depth = u32::MAX;
+        if is_red(&self.root) {
+            return (false, 0, 0, 0);
+        }
+        let cnt = match Self::_check(&self.root, (None, None), 1, &mut min_depth, &mut max_depth) {
+            Some(cnt) => cnt,
+            None => return (false, 0, 0, 0),
+        };
+        if self.iter().count() != self.cnt {
+            return (false, 0, 0, 0);
+        }
+
+        let is_rbt = cnt <= min_depth && cnt * 2 >= max_depth;
+        (is_rbt, cnt, min_depth, max_depth)
+    }
+}
+
+pub struct PersistentIter<'a, T> {
+    stack: Vec<&'a PNode<T>>,
+    used: usize, // how many times the top of stack has been yielded
+}
+
+impl<'a, T> PersistentIter<'a, T> {
+    fn push_left(&mut self, link: &'a Link<T>) {
+        let mut c = link;
+        while let Some(current) = c {
+            self.stack.push(current);
+            c = &current.lchild;
+        }
+    }
+}
+
+impl<'a, T> Iterator for PersistentIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = *self.stack.last()?;
+        self.used += 1;
+        if self.used == node.cnt {
+            self.stack.pop();
+            self.used = 0;
+            self.push_left(&node.rchild);
+        }
+        Some(&node.data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{model_test::test_rng, persistent::PersistentRBTree};
+    use rand::prelude::*;
+    #[test]
+    fn insert_delete_versions_test() {
+        let mut rng = test_rng();
+        let mut versions = vec![PersistentRBTree::<u64>::new()];
+        let mut models: Vec<Vec<u64>> = vec![vec![]];
+        for _ in 0..3000 {
+            let tree = versions.last().unwrap();
+            let mut model = models.last().unwrap().clone();
+            let n = rng.gen_range(0..500);
+            let next = if rng.gen_bool(0.6) {
+                model.push(n);
+                model.sort();
+                tree.insert(n)
+            } else {
+                if let Some(i) = model.iter().position(|&m| m == n) {
+                    model.remove(i);
+                }
+                tree.delete(&n)
+            };
+
+            let (is_rbt, blacks, min_depth, max_depth) = next.check();
+            assert!(is_rbt);
+            assert!(blacks <= min_depth);
+            assert!(blacks * 2 >= max_depth);
+            assert_eq!(next.len(), model.len());
+            versions.push(next);
+            models.push(model);
+        }
+
+        // every old version is left untouched
+        for (tree, model) in versions.iter().zip(models.iter()) {
+            assert!(tree.check().0);
+            assert!(tree.iter().eq(model.iter()));
+        }
+    }
+    #[test]
+    fn snapshot_test() {
+        let mut tree = PersistentRBTree::<u64>::new();
+        for n in 0..1000 {
+            tree = tree.insert(n);
+        }
+        let snapshot = tree.clone();
+        for n in 0..1000 {
+            tree = tree.delete(&n);
+            assert!(tree.check().0);
+        }
+        assert!(tree.is_empty());
+        assert_eq!(snapshot.len(), 1000);
+        assert!(snapshot.find(&500));
+        assert!(snapshot.iter().eq((0..1000).collect::<Vec<u64>>().iter()));
+
+        assert!(PersistentRBTree::<f64>::new().insert(f64::NAN).is_empty());
+        let tree = PersistentRBTree::<f64>::new()
+            .insert(1.0)
+            .insert(f64::NAN)
+            .insert(1.0);
+        assert_eq!(tree.len(), 2);
+        let tree = tree.delete(&1.0);
+        assert!(tree.find(&1.0));
+        assert!(!tree.delete(&1.0).find(&1.0));
+        assert_eq!(format!("{:?}", tree.insert(0.5)), "{0.5, 1.0}");
+    }
+}
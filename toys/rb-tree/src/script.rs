@@ -0,0 +1,285 @@
+use crate::tree::RBTree;
+use std::{
+    error::Error,
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+// one line of a script
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Insert(f64),
+    Delete(f64),
+    Find(f64),
+    Range(f64, f64), // values in [a, b]
+    Check,
+    Dump,
+}
+
+// how the tree is printed by `dump` and after each step
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Format {
+    Text,
+    Dot,
+}
+
+// how a script is replayed
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    pub format: Format,
+    pub show: bool,  // print the tree after every command
+    pub btree: bool, // print the 2-3-4 tree next to the red-black tree
+    pub trace: bool, // print the rotations and recolorings of inserts and deletes
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Parse { line: usize, message: String },
+    Io(io::Error),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            ScriptError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ScriptError {}
+
+impl From<io::Error> for ScriptError {
+    fn from(e: io::Error) -> Self {
+        ScriptError::Io(e)
+    }
+}
+
+// None for a blank line or a comment starting with '#'
+pub fn parse_line(line: &str) -> Result<Option<Command>, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (name, args) = match words.split_first() {
+        Some((name, _)) if name.starts_with('#') => return Ok(None),
+        Some((name, args)) => (*name, args),
+        None => return Ok(None),
+    };
+    let number = |word: &str| {
+        word.parse::<f64>()
+            .map_err(|_| format!("{:?} is not a number", word))
+    };
+    let command = match (name, args) {
+        ("insert", [x]) => Command::Insert(number(x)?),
+        ("delete", [x]) => Command::Delete(number(x)?),
+        ("find", [x]) => Command::Find(number(x)?),
+        ("range", [a, b]) => Command::Range(number(a)?, number(b)?),
+        ("check", []) => Command::Check,
+        ("dump", []) => Command::Dump,
+        ("insert" | "delete" | "find", _) => return Err(format!("usage: {} x", name)),
+        ("range", _) => return Err("usage: range a b".to_string()),
+        ("check" | "dump", _) => return Err(format!("{} takes no argument", name)),
+        _ => return Err(format!("unknown command {:?}", name)),
+    };
+    Ok(Some(command))
+}
+
+pub fn render(tree: &RBTree<f64>, options: &Options) -> String {
+    match (options.format, options.btree) {
+        (Format::Text, _) if tree.is_empty() => "(empty)\n".to_string(),
+        (Format::Text, false) => tree.to_ascii_art(),
+        (Format::Text, true) => tree.to_side_by_side(),
+        (Format::Dot, false) => tree.to_dot(),
+        (Format::Dot, true) => tree.to_dot_with_btree(),
+    }
+}
+
+// runs a command and prints its result after the command itself
+pub fn run(
+    tree: &mut RBTree<f64>,
+    command: &Command,
+    options: &Options,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let steps = match *command {
+        Command::Insert(x) => {
+            let (result, steps) = tree.traced(|tree| tree.try_insert(x));
+            match result {
+                Ok(()) => writeln!(out, "insert {:?}: ok", x)?,
+                Err(e) => writeln!(out, "insert {:?}: {}", x, e)?,
+            }
+            steps
+        }
+        Command::Delete(x) => {
+            let (deleted, steps) = tree.traced(|tree| tree.delete(&x));
+            let result = if deleted { "ok" } else { "not found" };
+            writeln!(out, "delete {:?}: {}", x, result)?;
+            steps
+        }
+        Command::Find(x) => {
+            let result = if tree.find(&x) { "found" } else { "not found" };
+            return writeln!(out, "find {:?}: {}", x, result);
+        }
+        Command::Range(a, b) => {
+            let values: Vec<&f64> = tree.range(a..=b).collect();
+            return writeln!(out, "range {:?} {:?}: {:?}", a, b, values);
+        }
+        Command::Check => {
+            return match tree.validate() {
+                Ok(stats) => writeln!(
+                    out,
+                    "check: ok ({} values, {} nodes, black height {}, depth {}..{})",
+                    stats.len, stats.nodes, stats.black_height, stats.min_depth, stats.max_depth
+                ),
+                Err(violations) => {
+                    writeln!(out, "check: {} violations", violations.len())?;
+                    for violation in violations {
+                        writeln!(out, "  {}", violation)?;
+                    }
+                    Ok(())
+                }
+            }
+        }
+        Command::Dump => return write!(out, "{}", render(tree, options)),
+    };
+    if options.trace {
+        for step in steps {
+            writeln!(out, "  {}", step)?;
+        }
+    }
+    Ok(())
+}
+
+// replays a script line by line, printing the tree after every command if `show`;
+// stops at the first line which is not a command
+pub fn replay(
+    input: impl BufRead,
+    out: &mut impl Write,
+    options: &Options,
+) -> Result<RBTree<f64>, ScriptError> {
+    let mut tree = RBTree::new();
+    for (i, line) in input.lines().enumerate() {
+        let command = match parse_line(&line?) {
+            Ok(Some(command)) => command,
+            Ok(None) => continue,
+            Err(message) => {
+                return Err(ScriptError::Parse {
+                    line: i + 1,
+                    message,
+                })
+            }
+        };
+        run(&mut tree, &command, options, out)?;
+        if options.show && command != Command::Dump {
+            write!(out, "{}", render(&tree, options))?;
+        }
+    }
+    out.flush()?;
+    Ok(tree)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::script::{parse_line, replay, Command, Format, Options, ScriptError};
+    fn options(format: Format, show: bool) -> Options {
+        Options {
+            format,
+            show,
+            btree: false,
+            trace: false,
+        }
+    }
+    #[test]
+    fn parse_line_test() {
+        assert_eq!(parse_line("insert 3"), Ok(Some(Command::Insert(3.0))));
+        assert_eq!(
+            parse_line("  delete -1.5  "),
+            Ok(Some(Command::Delete(-1.5)))
+        );
+        assert_eq!(parse_line("range 1 2"), Ok(Some(Command::Range(1.0, 2.0))));
+        assert_eq!(parse_line("check"), Ok(Some(Command::Check)));
+        assert_eq!(parse_line(""), Ok(None));
+        assert_eq!(parse_line("# insert 3"), Ok(None));
+        assert!(parse_line("insert").is_err());
+        assert!(parse_line("insert x").is_err());
+        assert!(parse_line("dump 3").is_err());
+        assert!(parse_line("remove 3").is_err());
+    }
+    #[test]
+    fn replay_test() {
+        let script = "# small tree\ninsert 2\ninsert 1\ninsert 3\ninsert 3\ninsert NaN\n\
+                      find 1\ndelete 1\ndelete 1\nrange 2 5\ncheck\ndump\n";
+        let mut out = vec![];
+        let tree = replay(script.as_bytes(), &mut out, &options(Format::Text, false)).unwrap();
+        assert_eq!(tree.len(), 3);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            [
+                "insert 2.0: ok",
+                "insert 1.0: ok",
+                "insert 3.0: ok",
+                "insert 3.0: ok",
+                "insert NaN: NaN is not comparable with values in the tree",
+                "find 1.0: found",
+                "delete 1.0: ok",
+                "delete 1.0: not found",
+                "range 2.0 5.0: [2.0, 3.0, 3.0]",
+                "check: ok (3 values, 2 nodes, black height 1, depth 1..2)",
+                "/-- 3.0(R) x2",
+                "2.0(B)",
+                "",
+            ]
+            .join("\n")
+        );
+
+        let mut out = vec![];
+        replay(
+            "insert 1\n".as_bytes(),
+            &mut out,
+            &options(Format::Dot, true),
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("insert 1.0: ok\ndigraph RBTree {\n"));
+
+        let err = replay(
+            "insert 1\nfind\n".as_bytes(),
+            &mut vec![],
+            &options(Format::Text, false),
+        );
+        assert!(matches!(err, Err(ScriptError::Parse { line: 2, .. })));
+    }
+    #[test]
+    fn replay_trace_test() {
+        let options = Options {
+            btree: true,
+            trace: true,
+            ..options(Format::Text, false)
+        };
+        let mut out = vec![];
+        replay(
+            "insert 1\ninsert 2\ninsert 3\ndump\n".as_bytes(),
+            &mut out,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            [
+                "insert 1.0: ok",
+                "  recolor 1.0 black",
+                "insert 2.0: ok",
+                "insert 3.0: ok",
+                "  case: red parent, black uncle, outer child",
+                "  recolor 1.0 red",
+                "  recolor 2.0 black",
+                "  rotate left: 2.0 up over 1.0",
+                "red-black     2-3-4",
+                "/-- 3.0(R)    [1.0 | 2.0 | 3.0]",
+                "2.0(B)",
+                "\\-- 1.0(R)",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use crate::tree::RBTree;
+use std::{
+    fmt::{self, Debug},
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+// RBTree shared between threads (read-mostly)
+// any number of readers run in parallel, a writer has the tree exclusively.
+// a panic while holding the lock poisons it, and later accesses panic as well
+// because the tree may be left half rebalanced.
+pub struct SharedRBTree<T: Debug> {
+    tree: RwLock<RBTree<T>>,
+}
+
+impl<T: Debug> Debug for SharedRBTree<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", *self.read())
+    }
+}
+
+impl<T: PartialOrd + Debug> Default for SharedRBTree<T> {
+    fn default() -> Self {
+        SharedRBTree::new()
+    }
+}
+
+impl<T: PartialOrd + Debug> SharedRBTree<T> {
+    pub fn new() -> SharedRBTree<T> {
+        SharedRBTree::from(RBTree::new())
+    }
+}
+
+impl<T: Debug> From<RBTree<T>> for SharedRBTree<T> {
+    fn from(tree: RBTree<T>) -> SharedRBTree<T> {
+        SharedRBTree {
+            tree: RwLock::new(tree),
+        }
+    }
+}
+
+impl<T: Debug> SharedRBTree<T> {
+    // holds the read lock while the guard lives, e.g. for iterating
+    pub fn read(&self) -> RwLockReadGuard<'_, RBTree<T>> {
+        self.tree.read().unwrap()
+    }
+
+    // holds the write lock while the guard lives, e.g. for several updates at once
+    pub fn write(&self) -> RwLockWriteGuard<'_, RBTree<T>> {
+        self.tree.write().unwrap()
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    pub fn find(&self, data: &T) -> bool {
+        self.read().find(data)
+    }
+
+    pub fn insert(&self, data: T) -> bool {
+        self.write().insert(data)
+    }
+
+    pub fn delete(&self, data: &T) -> bool {
+        self.write().delete(data)
+    }
+
+    pub fn check(&self) -> (bool, u32, u32, u32) {
+        self.read().check()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{model_test::test_rng, shared::SharedRBTree, tree::RBTree};
+    use rand::prelude::*;
+    use std::{sync::Arc, thread};
+    #[test]
+    fn send_sync_test() {
+        fn assert_send_sync<S: Send + Sync>() {}
+        assert_send_sync::<RBTree<u64>>();
+        assert_send_sync::<SharedRBTree<String>>();
+    }
+    #[test]
+    fn stress_test() {
+        const THREADS: u64 = 8;
+        const OPS: usize = 20000;
+        let tree = Arc::new(SharedRBTree::<u64>::new());
+        let mut rng = test_rng();
+        // each thread owns the values n with n % THREADS == id
+        // so it can tell what must be in the tree whatever the others do
+        let handles: Vec<_> = (0..THREADS)
+            .map(|id| {
+                let tree = Arc::clone(&tree);
+                let seed = rng.gen();
+                thread::spawn(move || {
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    let mut mine = vec![0usize; 1000];
+                    for _ in 0..OPS {
+                        let k = rng.gen_range(0..1000);
+                        let n = k as u64 * THREADS + id;
+                        match rng.gen_range(0..3) {
+                            0 => {
+                                assert!(tree.insert(n));
+                                mine[k] += 1;
+                            }
+                            1 => {
+                                assert_eq!(tree.delete(&n), mine[k] > 0);
+                                mine[k] = mine[k].saturating_sub(1);
+                            }
+                            _ => assert_eq!(tree.find(&n), mine[k] > 0),
+                        }
+                    }
+                    mine
+                })
+            })
+            .collect();
+        let owned: Vec<Vec<usize>> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        let (is_rbt, _, _, _) = tree.check();
+        assert!(is_rbt);
+        assert!(tree.read().validate().is_ok());
+        let total: usize = owned.iter().flatten().sum();
+        assert_eq!(tree.len(), total);
+        for (id, mine) in owned.iter().enumerate() {
+            for (k, &cnt) in mine.iter().enumerate() {
+                let n = k as u64 * THREADS + id as u64;
+                assert_eq!(tree.read().count_range(n..=n), cnt);
+            }
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use crate::ordered_set::{self, rotate_left, rotate_right, BoxIter, BoxLink, BoxNode, OrderedSet};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+};
+
+// treap: a search tree by value and a max-heap by a random priority, so its
+// shape is that of a tree built from the values in random order
+pub struct Treap<T> {
+    root: BoxLink<T, u64>, // balance of a node is its priority
+    cnt: usize,
+    seed: u64, // state of the priority generator
+}
+
+fn priority<T>(link: &BoxLink<T, u64>) -> Option<u64> {
+    link.as_ref().map(|node| node.balance)
+}
+
+fn insert_at<T: PartialOrd>(link: &mut BoxLink<T, u64>, data: T, new_priority: u64) -> bool {
+    let node = match link {
+        Some(node) => node,
+        None if !ordered_set::comparable(&data) => return false,
+        None => {
+            *link = Some(BoxNode::new(data, new_priority));
+            return true;
+        }
+    };
+    match data.partial_cmp(&node.data) {
+        Some(Ordering::Less) => {
+            if !insert_at(&mut node.lchild, data, new_priority) {
+                return false;
+            }
+            if priority(&node.lchild) > Some(node.balance) {
+                *link = link.take().map(rotate_right);
+            }
+        }
+        Some(Ordering::Greater) => {
+            if !insert_at(&mut node.rchild, data, new_priority) {
+                return false;
+            }
+            if priority(&node.rchild) > Some(node.balance) {
+                *link = link.take().map(rotate_left);
+            }
+        }
+        Some(Ordering::Equal) => node.cnt += 1,
+        None => return false,
+    }
+    true
+}
+
+fn delete_at<T: PartialOrd>(link: &mut BoxLink<T, u64>, data: &T) -> bool {
+    let node = match link {
+        Some(node) => node,
+        None => return false,
+    };
+    match data.partial_cmp(&node.data) {
+        Some(Ordering::Less) => delete_at(&mut node.lchild, data),
+        Some(Ordering::Greater) => delete_at(&mut node.rchild, data),
+        Some(Ordering::Equal) if node.cnt > 1 => {
+            node.cnt -= 1;
+            true
+        }
+        Some(Ordering::Equal) => {
+            // rotates the node down below the child of higher priority
+            // until it has at most one child
+            match (priority(&node.lchild), priority(&node.rchild)) {
+                (None, _) => *link = node.rchild.take(),
+                (_, None) => *link = node.lchild.take(),
+                (Some(l), Some(r)) if l > r => {
+                    let top = rotate_right(link.take().unwrap());
+                    delete_at(&mut link.insert(top).rchild, data);
+                }
+                _ => {
+                    let top = rotate_left(link.take().unwrap());
+                    delete_at(&mut link.insert(top).lchild, data);
+                }
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+// checks that no node has a higher priority than its parent; returns the height
+fn check_heap<T: Debug>(link: &BoxLink<T, u64>) -> Result<u32, String> {
+    let node = match link {
+        Some(node) => node,
+        None => return Ok(0),
+    };
+    for child in [&node.lchild, &node.rchild].into_iter().flatten() {
+        if child.balance > node.balance {
+            return Err(format!(
+                "{:?} has a higher priority than its parent {:?}",
+                child.data, node.data
+            ));
+        }
+    }
+    Ok(1 + check_heap(&node.lchild)?.max(check_heap(&node.rchild)?))
+}
+
+impl<T: Debug> Debug for Treap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(BoxIter::new(&self.root)).finish()
+    }
+}
+
+impl<T: PartialOrd + Debug> Default for Treap<T> {
+    fn default() -> Self {
+        Treap::new()
+    }
+}
+
+impl<T: PartialOrd + Debug> Treap<T> {
+    pub fn new() -> Treap<T> {
+        Self::with_seed(0x9e37_79b9_7f4a_7c15)
+    }
+
+    // the same seed and operations give the same shape
+    pub fn with_seed(seed: u64) -> Treap<T> {
+        Treap {
+            root: None,
+            cnt: 0,
+            seed: seed | 1, // xorshift never leaves 0
+        }
+    }
+
+    fn next_priority(&mut self) -> u64 {
+        // xorshift64
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed
+    }
+
+    // (is treap or not, height)
+    pub fn check(&self) -> (bool, u32) {
+        match self.validate() {
+            Ok(height) => (true, height),
+            Err(_) => (false, 0),
+        }
+    }
+
+    fn validate(&self) -> Result<u32, String> {
+        let cnt = ordered_set::check_order(&self.root, None, None)?;
+        if cnt != self.cnt {
+            return Err(format!("{} values stored, but len is {}", cnt, self.cnt));
+        }
+        check_heap(&self.root)
+    }
+}
+
+impl<T: PartialOrd + Debug> OrderedSet<T> for Treap<T> {
+    type Iter<'a>
+        = BoxIter<'a, T, u64>
+    where
+        T: 'a;
+
+    fn new() -> Self {
+        Treap::new()
+    }
+    fn len(&self) -> usize {
+        self.cnt
+    }
+    fn insert(&mut self, data: T) -> bool {
+        let new_priority = self.next_priority();
+        let inserted = insert_at(&mut self.root, data, new_priority);
+        self.cnt += inserted as usize;
+        inserted
+    }
+    fn delete(&mut self, data: &T) -> bool {
+        let deleted = delete_at(&mut self.root, data);
+        self.cnt -= deleted as usize;
+        deleted
+    }
+    fn find(&self, data: &T) -> bool {
+        ordered_set::find_in(&self.root, data)
+    }
+    fn min(&self) -> Option<&T> {
+        ordered_set::min_in(&self.root)
+    }
+    fn max(&self) -> Option<&T> {
+        ordered_set::max_in(&self.root)
+    }
+    fn iter(&self) -> BoxIter<'_, T, u64> {
+        BoxIter::new(&self.root)
+    }
+    fn height(&self) -> u32 {
+        self.check().1
+    }
+    fn check_invariants(&self) -> Result<(), String> {
+        self.validate().map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ordered_set::OrderedSet, treap::Treap};
+    #[test]
+    fn seed_test() {
+        // ascending inserts still give a tree of logarithmic height
+        let mut a = Treap::<u32>::with_seed(1);
+        let mut b = Treap::<u32>::with_seed(1);
+        for n in 0..10000 {
+            a.insert(n);
+            b.insert(n);
+        }
+        let (is_treap, height) = a.check();
+        assert!(is_treap && height < 60);
+        assert_eq!(b.check(), (true, height));
+        assert!(a.insert(0));
+        assert_eq!(a.len(), 10001);
+        let mut floats = Treap::<f64>::new();
+        assert!(floats.insert(1.0));
+        assert!(!floats.insert(f64::NAN));
+        assert_eq!(floats.len(), 1);
+    }
+}
@@ -1,49 +1,41 @@
-// use debug_cell::RefCell;
-use std::cell::RefCell;
+pub mod btree_view;
+mod bulk;
+pub mod cursor;
+pub mod dump;
+pub mod entry;
+pub mod interval;
+mod render;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod set_ops;
+pub mod trace;
+
 use std::{
     cmp::Ordering,
+    collections::VecDeque,
+    error::Error,
     fmt::{self, Debug},
-    rc::Rc,
+    iter::FusedIterator,
+    ops::{Bound, RangeBounds},
+    sync::Arc,
 };
 
-#[derive(Copy, Clone, Debug)]
-enum Color {
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Color {
     Red,
     Black,
 }
 
-struct RBTreeNode<T: PartialOrd + Debug> {
+// nodes live in the slots of `RBTree::nodes` and link each other by slot index
+#[derive(Clone)]
+struct RBTreeNode<T: Debug> {
     data: T,
     cnt: usize,
+    size: usize, // sum of `cnt` over the subtree rooted at this node
     color: Color,
-    parent: Option<Rc<RefCell<RBTreeNode<T>>>>,
-    lchild: Option<Rc<RefCell<RBTreeNode<T>>>>,
-    rchild: Option<Rc<RefCell<RBTreeNode<T>>>>,
-}
-
-impl<T: PartialOrd + Debug> Debug for RBTreeNode<T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let parent = if let Some(parent) = &self.parent {
-            format!("{:?}", parent.borrow().data)
-        } else {
-            String::from("none")
-        };
-        let lchild = if let Some(child) = &self.lchild {
-            format!("{:?}", child.borrow().data)
-        } else {
-            String::from("none")
-        };
-        let rchild = if let Some(child) = &self.rchild {
-            format!("{:?}", child.borrow().data)
-        } else {
-            String::from("none")
-        };
-        write!(
-            f,
-            "Node({:?};{:?};p({});l({});r({}))",
-            self.data, self.color, parent, lchild, rchild
-        )
-    }
+    parent: Option<usize>,
+    lchild: Option<usize>,
+    rchild: Option<usize>,
 }
 
 enum Child {
@@ -52,11 +44,18 @@ enum Child {
     None,
 }
 
-impl<T: PartialOrd + Debug> RBTreeNode<T> {
+// result of `RBTree::search`
+enum Place {
+    Found(usize),
+    Vacant(Option<usize>, Child), // parent (None for root) and side of new node
+}
+
+impl<T: Debug> RBTreeNode<T> {
     pub fn new(data: T) -> RBTreeNode<T> {
         RBTreeNode {
             data,
             cnt: 1,
+            size: 1,
             color: Color::Red,
             parent: None,
             lchild: None,
@@ -64,95 +63,69 @@ impl<T: PartialOrd + Debug> RBTreeNode<T> {
         }
     }
 
-    // is node left child or right child of its parent
-    fn which_child(node: Rc<RefCell<RBTreeNode<T>>>) -> Child {
-        if let Some(parent) = &node.borrow().parent {
-            let left = parent.borrow().lchild.clone();
-            let right = parent.borrow().rchild.clone();
-            match (left, right) {
-                (Some(child), None) if Rc::ptr_eq(&child, &node) => Child::Left,
-                (None, Some(child)) if Rc::ptr_eq(&child, &node) => Child::Right,
-                (Some(lchild), Some(rchild)) => {
-                    if Rc::ptr_eq(&lchild, &node) {
-                        Child::Left
-                    } else if Rc::ptr_eq(&rchild, &node) {
-                        Child::Right
-                    } else {
-                        Child::None
-                    }
-                }
-                (_, _) => Child::None,
-            }
-        } else {
-            Child::None
-        }
+    fn is_root(&self) -> bool {
+        self.parent.is_none()
     }
+}
 
-    fn get_childs_string(&self) -> String {
-        let data = &self.data;
-        let parent = match &self.parent {
-            Some(var) => format!("{:?}", var.borrow().data),
-            None => "_".to_string(),
-        };
-        let lchild = match &self.lchild {
-            Some(child) => format!("{:?}", child.borrow().data),
-            None => "_".to_string(),
-        };
-        let rchild = match &self.rchild {
-            Some(child) => format!("{:?}", child.borrow().data),
-            None => "_".to_string(),
-        };
-        format!(
-            "[{:?}({:?}): (p{:?},l{:?},r{:?})]",
-            data, self.color, parent, lchild, rchild
-        )
-    }
+type TotalCmp<T> = dyn Fn(&T, &T) -> Ordering + Send + Sync;
 
-    fn get_childs_string_chain(&self) -> String {
-        let mut result = String::new();
-        result.push_str(self.get_childs_string().as_str());
-        result.push('\n');
-        if let Some(ref child) = self.lchild {
-            result.push_str(child.borrow().get_childs_string_chain().as_str());
-        }
-        if let Some(ref child) = self.rchild {
-            result.push_str(child.borrow().get_childs_string_chain().as_str());
-        }
-        result
-    }
+// how values are ordered in a tree
+enum Comparator<T> {
+    Partial(fn(&T, &T) -> Option<Ordering>),
+    Total(Arc<TotalCmp<T>>),
+}
 
-    fn is_root(&self) -> bool {
-        self.parent.is_none()
-    }
+// recalculates the data of a node from the data of its children, wherever
+// `size` is recalculated (e.g. the max endpoint of an interval tree)
+type Augment<T> = fn(&mut T, Option<&T>, Option<&T>);
 
-    // get parent's sibling
-    fn get_uncle(&self) -> Option<Rc<RefCell<RBTreeNode<T>>>> {
-        if let Some(parent) = self.parent.clone() {
-            if let Some(grand_parent) = parent.borrow().parent.clone() {
-                match Self::which_child(parent.clone()) {
-                    Child::Left => return grand_parent.borrow().rchild.clone(),
-                    Child::Right => return grand_parent.borrow().lchild.clone(),
-                    Child::None => return None,
-                }
-            }
+impl<T> Clone for Comparator<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Comparator::Partial(cmp) => Comparator::Partial(*cmp),
+            Comparator::Total(cmp) => Comparator::Total(cmp.clone()),
         }
+    }
+}
 
-        None
+// a value could not be ordered against the values in the tree (e.g. NaN)
+#[derive(Clone, Debug, PartialEq)]
+pub struct IncomparableError<T> {
+    pub data: T,
+}
+
+impl<T: Debug> fmt::Display for IncomparableError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} is not comparable with values in the tree",
+            self.data
+        )
     }
 }
 
-pub struct RBTree<T: PartialOrd + Debug> {
-    root: Option<Rc<RefCell<RBTreeNode<T>>>>,
+impl<T: Debug> Error for IncomparableError<T> {}
+
+// nodes are owned by the arena, not by their parents, so dropping a tree
+// frees them slot by slot without recursing down its height
+pub struct RBTree<T: Debug> {
+    nodes: Vec<Option<RBTreeNode<T>>>, // arena of node slots
+    free: Vec<usize>,                  // indexes of empty slots in `nodes`
+    root: Option<usize>,
     cnt: usize,
+    cmp: Comparator<T>,
+    augment: Option<Augment<T>>,
+    trace: Option<Vec<trace::Step>>, // steps of rebalancing, inside `traced`
 }
 
-impl<T: PartialOrd + Debug> Debug for RBTree<T> {
+impl<T: Debug> Debug for RBTree<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Some(ref root) = &self.root {
+        if let Some(root) = self.root {
             write!(
                 f,
                 "{}\n{} nodes",
-                root.borrow().get_childs_string_chain().trim(),
+                self.get_childs_string_chain(root).trim(),
                 self.cnt,
             )
         } else {
@@ -162,310 +135,605 @@ impl<T: PartialOrd + Debug> Debug for RBTree<T> {
 }
 
 impl<T: PartialOrd + Debug> RBTree<T> {
+    // ordered by `PartialOrd`; values like NaN are refused
     pub fn new() -> RBTree<T> {
-        RBTree { root: None, cnt: 0 }
+        Self::with_cmp(Comparator::Partial(T::partial_cmp))
+    }
+}
+
+impl<T: Ord + Debug> RBTree<T> {
+    // ordered by `Ord`; every value can be inserted
+    pub fn new_ord() -> RBTree<T> {
+        Self::with_cmp(Comparator::Partial(|a, b| Some(a.cmp(b))))
+    }
+}
+
+impl<T: Debug> RBTree<T> {
+    // ordered by cmp (e.g. by a derived key, reversed, case-insensitive);
+    // values which cmp finds equal share a node as duplicates
+    pub fn with_comparator(cmp: impl Fn(&T, &T) -> Ordering + Send + Sync + 'static) -> RBTree<T> {
+        Self::with_cmp(Comparator::Total(Arc::new(cmp)))
+    }
+
+    fn with_cmp(cmp: Comparator<T>) -> RBTree<T> {
+        RBTree {
+            nodes: vec![],
+            free: vec![],
+            root: None,
+            cnt: 0,
+            cmp,
+            augment: None,
+            trace: None,
+        }
+    }
+
+    fn compare(&self, a: &T, b: &T) -> Option<Ordering> {
+        match &self.cmp {
+            Comparator::Partial(cmp) => cmp(a, b),
+            Comparator::Total(cmp) => Some(cmp(a, b)),
+        }
+    }
+
+    // a value not equal to itself (e.g. NaN) has no place in the order
+    fn comparable(&self, data: &T) -> bool {
+        match &self.cmp {
+            Comparator::Partial(cmp) => cmp(data, data) == Some(Ordering::Equal),
+            Comparator::Total(_) => true,
+        }
+    }
+
+    fn lt(&self, a: &T, b: &T) -> bool {
+        matches!(self.compare(a, b), Some(Ordering::Less))
+    }
+
+    fn le(&self, a: &T, b: &T) -> bool {
+        matches!(self.compare(a, b), Some(Ordering::Less | Ordering::Equal))
     }
 
     pub fn len(&self) -> usize {
         self.cnt
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.cnt == 0
+    }
+
+    // iterates in ascending order, yielding each value `cnt` times
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            tree: self,
+            front: self.root.map(|root| self.min_node(root)),
+            front_used: 0,
+            back: self.root.map(|root| self.max_node(root)),
+            back_used: 0,
+            remaining: self.cnt,
+        }
+    }
+
+    // iterates in ascending order over values within the bounds
+    pub fn range(&self, range: impl RangeBounds<T>) -> Iter<'_, T> {
+        self.range_by(
+            |data| match range.start_bound() {
+                Bound::Included(start) => self.lt(data, start),
+                Bound::Excluded(start) => self.le(data, start),
+                Bound::Unbounded => false,
+            },
+            |data| match range.end_bound() {
+                Bound::Included(end) => self.le(data, end),
+                Bound::Excluded(end) => self.lt(data, end),
+                Bound::Unbounded => true,
+            },
+        )
+    }
+
+    // iterates over values after the prefix `before` and within the prefix
+    // `until` (both must hold for a leading run of the sorted values only)
+    pub(crate) fn range_by(
+        &self,
+        before: impl Fn(&T) -> bool,
+        until: impl Fn(&T) -> bool,
+    ) -> Iter<'_, T> {
+        Iter {
+            tree: self,
+            front: self.first_node_not(&before),
+            front_used: 0,
+            back: self.last_node_of(&until),
+            back_used: 0,
+            remaining: self
+                .count_prefix(&until)
+                .saturating_sub(self.count_prefix(&before)),
+        }
+    }
+
+    // number of values within the bounds in O(log n)
+    pub fn count_range(&self, range: impl RangeBounds<T>) -> usize {
+        let before_start = match range.start_bound() {
+            Bound::Included(start) => self.count_prefix(|data| self.lt(data, start)),
+            Bound::Excluded(start) => self.count_prefix(|data| self.le(data, start)),
+            Bound::Unbounded => 0,
+        };
+        let until_end = match range.end_bound() {
+            Bound::Included(end) => self.count_prefix(|data| self.le(data, end)),
+            Bound::Excluded(end) => self.count_prefix(|data| self.lt(data, end)),
+            Bound::Unbounded => self.cnt,
+        };
+        until_end.saturating_sub(before_start)
+    }
+
+    // k-th smallest value (0-based, duplicates counted) in O(log n)
+    pub fn select(&self, k: usize) -> Option<&T> {
+        let mut k = k;
+        let mut c = self.root;
+        while let Some(current) = c {
+            let current_node = self.node(current);
+            let lsize = self.get_size(current_node.lchild);
+            if k < lsize {
+                c = current_node.lchild;
+            } else if k < lsize + current_node.cnt {
+                return Some(&current_node.data);
+            } else {
+                k -= lsize + current_node.cnt;
+                c = current_node.rchild;
+            }
+        }
+        None
+    }
+
+    // number of values less than data in O(log n)
+    pub fn rank(&self, data: &T) -> usize {
+        self.count_prefix(|d| self.lt(d, data))
+    }
+
+    pub fn min(&self) -> Option<&T> {
+        let root = self.root?;
+        Some(&self.node(self.min_node(root)).data)
+    }
+
+    pub fn max(&self) -> Option<&T> {
+        let root = self.root?;
+        Some(&self.node(self.max_node(root)).data)
+    }
+
+    // greatest value less than or equal to data
+    pub fn floor(&self, data: &T) -> Option<&T> {
+        let node = self.last_node_of(|d| self.le(d, data))?;
+        Some(&self.node(node).data)
+    }
+
+    // least value greater than or equal to data
+    pub fn ceiling(&self, data: &T) -> Option<&T> {
+        let node = self.first_node_not(|d| self.lt(d, data))?;
+        Some(&self.node(node).data)
+    }
+
+    // first value not less than data (same as ceiling)
+    pub fn lower_bound(&self, data: &T) -> Option<&T> {
+        self.ceiling(data)
+    }
+
+    // first value greater than data (same as successor)
+    pub fn upper_bound(&self, data: &T) -> Option<&T> {
+        self.successor(data)
+    }
+
+    // greatest value less than data
+    pub fn predecessor(&self, data: &T) -> Option<&T> {
+        let node = self.last_node_of(|d| self.lt(d, data))?;
+        Some(&self.node(node).data)
+    }
+
+    // least value greater than data
+    pub fn successor(&self, data: &T) -> Option<&T> {
+        let node = self.first_node_not(|d| self.le(d, data))?;
+        Some(&self.node(node).data)
+    }
+
+    // every node's value with its cnt, in ascending order
+    fn node_iter(&self) -> impl Iterator<Item = (&T, usize)> {
+        let first = self.root.map(|root| self.min_node(root));
+        std::iter::successors(first, |&node| self.next_node(node))
+            .map(|node| (&self.node(node).data, self.node(node).cnt))
+    }
+
+    // how many values are in the prefix
+    // (`in_prefix` must hold for a leading run of the sorted values only)
+    fn count_prefix(&self, in_prefix: impl Fn(&T) -> bool) -> usize {
+        let mut cnt = 0;
+        let mut c = self.root;
+        while let Some(current) = c {
+            let current_node = self.node(current);
+            if in_prefix(&current_node.data) {
+                cnt += self.get_size(current_node.lchild) + current_node.cnt;
+                c = current_node.rchild;
+            } else {
+                c = current_node.lchild;
+            }
+        }
+        cnt
+    }
+
+    // first node out of the prefix
+    fn first_node_not(&self, in_prefix: impl Fn(&T) -> bool) -> Option<usize> {
+        let mut found = None;
+        let mut c = self.root;
+        while let Some(current) = c {
+            if in_prefix(&self.node(current).data) {
+                c = self.node(current).rchild;
+            } else {
+                c = self.node(current).lchild;
+                found = Some(current);
+            }
+        }
+        found
+    }
+
+    // last node in the prefix
+    fn last_node_of(&self, in_prefix: impl Fn(&T) -> bool) -> Option<usize> {
+        let mut found = None;
+        let mut c = self.root;
+        while let Some(current) = c {
+            if in_prefix(&self.node(current).data) {
+                c = self.node(current).rchild;
+                found = Some(current);
+            } else {
+                c = self.node(current).lchild;
+            }
+        }
+        found
+    }
+
     pub fn find(&self, data: &T) -> bool {
-        if let Some(root) = &self.root {
-            if let Some(node) = Self::_find_node(root.clone(), data) {
-                if let Some(Ordering::Equal) = node.borrow().data.partial_cmp(data) {
-                    return true;
-                }
+        if let Some(node) = self.find_node(data) {
+            if let Some(Ordering::Equal) = self.compare(&self.node(node).data, data) {
+                return true;
             }
         }
         false
     }
 
+    // returns false if data is not comparable
     pub fn insert(&mut self, data: T) -> bool {
-        let new_node = Rc::new(RefCell::new(RBTreeNode::new(data)));
+        self.try_insert(data).is_ok()
+    }
+
+    pub fn try_insert(&mut self, data: T) -> Result<(), IncomparableError<T>> {
+        match self.insert_unique(data) {
+            Ok((_, None)) => Ok(()),
+            Ok((node, Some(_))) => {
+                self.node_mut(node).cnt += 1;
+                self.update_size_upward(Some(node));
+                self.cnt += 1;
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    // insert data into the tree and get the node and data,
+    // which is given back if an equal node already exists, or
+    // `None` if data is not comparable
+    pub(crate) fn insert_or_get(&mut self, data: T) -> Option<(&mut T, Option<T>)> {
+        let (node, data) = self.insert_unique(data).ok()?;
+        Some((&mut self.node_mut(node).data, data))
+    }
+
+    // link data as a new node unless an equal node already exists
+    fn insert_unique(&mut self, data: T) -> Result<(usize, Option<T>), IncomparableError<T>> {
+        match self.search(&data) {
+            Some(Place::Found(node)) => Ok((node, Some(data))),
+            Some(Place::Vacant(parent, side)) => Ok((self.link(parent, side, data, 1), None)),
+            None => Err(IncomparableError { data }),
+        }
+    }
+
+    // the node equal to data, or where data would be linked; `None` if data
+    // is not comparable
+    fn search(&self, data: &T) -> Option<Place> {
         let mut p = None; // tracing parent node for new node
-        let mut c = self.root.clone(); // tracing current node
+        let mut side = Child::None;
+        let mut c = self.root; // tracing current node
 
         // find out parent node for new node
-        while let Some(current) = c.clone() {
-            p = Some(current.clone());
-            let mut current_borrowed = current.borrow_mut();
-            match new_node.borrow().data.partial_cmp(&current_borrowed.data) {
-                Some(order) => match order {
-                    Ordering::Less => c = current_borrowed.lchild.clone(),
-                    Ordering::Greater => c = current_borrowed.rchild.clone(),
-                    Ordering::Equal => {
-                        current_borrowed.cnt += 1;
-                        self.cnt += 1;
-                        return true;
-                    }
-                },
-                None => return false,
+        while let Some(current) = c {
+            p = Some(current);
+            let current_node = self.node(current);
+            match self.compare(data, &current_node.data)? {
+                Ordering::Less => (c, side) = (current_node.lchild, Child::Left),
+                Ordering::Greater => (c, side) = (current_node.rchild, Child::Right),
+                Ordering::Equal => return Some(Place::Found(current)),
             }
         }
+        // nothing was compared on an empty tree
+        if !self.comparable(data) {
+            return None;
+        }
+        Some(Place::Vacant(p, side))
+    }
+
+    // link data (cnt copies) as a new node at a vacant place found by `search`
+    fn link(&mut self, parent: Option<usize>, side: Child, data: T, cnt: usize) -> usize {
+        let mut new_node = RBTreeNode::new(data);
+        new_node.cnt = cnt;
+        let new_node = self.alloc(new_node);
 
         // set new node to parent as a child
-        if let Some(parent) = p {
-            let mut new_node_borrowed = (*new_node).borrow_mut();
-            let mut parent_borrowed = (*parent).borrow_mut();
-            if new_node_borrowed.data < parent_borrowed.data {
-                parent_borrowed.lchild = Some(new_node.clone());
-                new_node_borrowed.parent = Some(parent.clone());
-            } else {
-                parent_borrowed.rchild = Some(new_node.clone());
-                new_node_borrowed.parent = Some(parent.clone());
+        if let Some(parent) = parent {
+            match side {
+                Child::Left => self.node_mut(parent).lchild = Some(new_node),
+                _ => self.node_mut(parent).rchild = Some(new_node),
             }
+            self.node_mut(new_node).parent = Some(parent);
         } else {
             // root has no parent
-            self.root = Some(new_node.clone());
+            self.root = Some(new_node);
         }
 
-        self.cnt += 1;
+        self.update_size_upward(Some(new_node));
+        self.cnt += cnt;
 
         // fix balance of rb tree
-        self.fix_insert(new_node.clone());
+        self.fix_insert(new_node);
 
-        true
+        new_node
     }
 
     pub fn delete(&mut self, data: &T) -> bool {
-        let found = self.find_node(data);
-        if let Some(found_node) = found.clone() {
-            let cnt = found_node.borrow().cnt;
-            if cnt > 1 {
-                found_node.borrow_mut().cnt -= 1;
+        if let Some(found_node) = self.find_node(data) {
+            if self.node(found_node).cnt > 1 {
+                self.node_mut(found_node).cnt -= 1;
+                self.update_size_upward(Some(found_node));
                 self.cnt -= 1;
                 return true;
             }
 
-            // println!("delete({:?})", found_node.borrow().data);
-            let found_node_color = found_node.borrow().color;
+            self.delete_node(found_node).is_some()
+        } else {
+            false
+        }
+    }
 
-            let mut has_2_children = found_node.borrow().lchild.is_some();
-            has_2_children = has_2_children && found_node.borrow().rchild.is_some();
+    // remove the node matched by cmp (regardless of its cnt) and get its data
+    pub(crate) fn remove_by(&mut self, cmp: impl Fn(&T) -> Option<Ordering>) -> Option<T> {
+        let found_node = self.find_node_by(cmp)?;
+        self.delete_node(found_node)
+    }
 
-            if has_2_children {
-                let successor = self.find_successor(found_node.clone());
-                let successor_color = Self::get_color(successor.clone());
+    // unlink a node (with all its cnt) from the tree, fix balance and
+    // get the data out of the node
+    fn delete_node(&mut self, found_node: usize) -> Option<T> {
+        let found_node_color = self.node(found_node).color;
 
-                let (starting, parent) = if let Some(successor_node) = &successor {
-                    successor_node.borrow_mut().color = found_node_color;
-                    match RBTreeNode::<T>::which_child(successor_node.clone()) {
-                        Child::Left => {
-                            let starting = successor_node.borrow().rchild.clone();
-                            let parent = successor_node.borrow().parent.clone();
-                            // println!("2 children, successor left, st({:?}) p({:?})", starting, parent);
-                            self.transplant(found_node.clone(), successor_node.clone(), true);
-                            // println!("2 children, successor left, st({:?}) p({:?})", starting, parent);
-                            (starting, parent)
-                        }
-                        Child::Right => {
-                            let starting = successor_node.borrow().rchild.clone();
-                            let parent = successor.clone();
-                            // println!("2 children, successor left, st({:?}) p({:?})", starting, parent);
-                            self.transplant(found_node.clone(), successor_node.clone(), false);
-                            // println!("2 children, successor left, st({:?}) p({:?})", starting, parent);
-                            (starting, parent)
-                        }
-                        Child::None => return false,
-                    }
-                } else {
-                    return false;
-                };
+        let has_2_children =
+            self.node(found_node).lchild.is_some() && self.node(found_node).rchild.is_some();
 
-                if let Color::Black = successor_color {
-                    self.fix_delete(starting, parent);
-                }
-            } else {
-                // println!("1|0 child");
-                let child = if found_node.borrow().lchild.is_some() {
-                    found_node.borrow().lchild.clone()
-                } else {
-                    found_node.borrow().rchild.clone()
-                };
+        if has_2_children {
+            let successor = self.find_successor(found_node);
+            let successor_color = self.get_color(successor);
 
-                let pfound = found_node.borrow().parent.clone();
-                match RBTreeNode::<T>::which_child(found_node.clone()) {
+            let (starting, parent) = if let Some(successor_node) = successor {
+                self.set_color(successor_node, found_node_color);
+                match self.which_child(successor_node) {
                     Child::Left => {
-                        // println!("left p{:?} c{:?}", pfound, child);
-                        (*pfound.clone().unwrap()).borrow_mut().lchild = child.clone();
+                        let starting = self.node(successor_node).rchild;
+                        let parent = self.node(successor_node).parent;
+                        self.transplant(found_node, successor_node, true);
+                        (starting, parent)
                     }
                     Child::Right => {
-                        // println!("right p{:?} c{:?}", pfound, child);
-                        (*pfound.clone().unwrap()).borrow_mut().rchild = child.clone();
+                        let starting = self.node(successor_node).rchild;
+                        let parent = successor;
+                        self.transplant(found_node, successor_node, false);
+                        (starting, parent)
                     }
-                    Child::None => {
-                        self.root = child.clone();
-                    }
-                }
-                if let Some(child_node) = &child {
-                    (*child_node).borrow_mut().parent = pfound.clone();
-                }
-
-                // Red => No broken rules
-                if let Color::Black = found_node_color {
-                    let pfound = found_node.borrow().parent.clone();
-                    self.fix_delete(child, pfound);
+                    Child::None => return None,
                 }
-            }
+            } else {
+                return None;
+            };
 
-            self.cnt -= 1;
+            // sizes below `parent` are untouched by the transplant
+            self.update_size_upward(parent);
 
-            true
+            if let Color::Black = successor_color {
+                self.fix_delete(starting, parent);
+            }
         } else {
-            false
+            let child = if self.node(found_node).lchild.is_some() {
+                self.node(found_node).lchild
+            } else {
+                self.node(found_node).rchild
+            };
+
+            let pfound = self.node(found_node).parent;
+            match self.which_child(found_node) {
+                Child::Left => self.node_mut(pfound.unwrap()).lchild = child,
+                Child::Right => self.node_mut(pfound.unwrap()).rchild = child,
+                Child::None => self.root = child,
+            }
+            if let Some(child_node) = child {
+                self.node_mut(child_node).parent = pfound;
+            }
+            self.update_size_upward(pfound);
+
+            // Red => No broken rules
+            if let Color::Black = found_node_color {
+                self.fix_delete(child, pfound);
+            }
         }
+
+        let found = self.dealloc(found_node);
+        self.cnt -= found.cnt;
+
+        Some(found.data)
     }
 
     //   P           X
     //     X  =>   P
     //   C           C
-    fn rotate_left(&mut self, node: Rc<RefCell<RBTreeNode<T>>>) {
-        let parent = match node.borrow().parent.clone() {
-            Some(p) => p.clone(),
+    fn rotate_left(&mut self, node: usize) {
+        let parent = match self.node(node).parent {
+            Some(p) => p,
             None => {
-                self.root = Some(node.clone());
+                self.root = Some(node);
                 return;
             }
         };
-        let grand_parent = parent.borrow().parent.clone();
-        let lchild_of_node = node.borrow().lchild.clone();
+        self.record(|tree| trace::Step::RotateLeft {
+            node: format!("{:?}", tree.node(node).data),
+            parent: format!("{:?}", tree.node(parent).data),
+        });
+        let grand_parent = self.node(parent).parent;
+        let lchild_of_node = self.node(node).lchild;
 
         // P<--C
-        if let Some(lc) = &lchild_of_node {
-            (*lc).borrow_mut().parent = Some(parent.clone());
+        if let Some(lc) = lchild_of_node {
+            self.node_mut(lc).parent = Some(parent);
         }
 
         // GP<-->X
-        if let Some(gp) = grand_parent.clone() {
-            match RBTreeNode::<T>::which_child(parent.clone()) {
-                Child::Left => (*gp).borrow_mut().lchild = Some(node.clone()),
-                Child::Right => (*gp).borrow_mut().rchild = Some(node.clone()),
+        if let Some(gp) = grand_parent {
+            match self.which_child(parent) {
+                Child::Left => self.node_mut(gp).lchild = Some(node),
+                Child::Right => self.node_mut(gp).rchild = Some(node),
                 _ => {}
             }
         } else {
-            self.root = Some(node.clone());
+            self.root = Some(node);
         }
-        (*node).borrow_mut().parent = grand_parent.clone();
+        self.node_mut(node).parent = grand_parent;
 
         // X<-->P-->C
-        (*parent).borrow_mut().parent = Some(node.clone());
-        (*parent).borrow_mut().rchild = lchild_of_node.clone();
-        (*node).borrow_mut().lchild = Some(parent.clone());
+        self.node_mut(parent).parent = Some(node);
+        self.node_mut(parent).rchild = lchild_of_node;
+        self.node_mut(node).lchild = Some(parent);
+
+        self.update_size(parent);
+        self.update_size(node);
     }
 
     //   P      X
     // X    =>    P
     //   C      C
-    fn rotate_right(&mut self, node: Rc<RefCell<RBTreeNode<T>>>) {
-        let parent = match node.borrow().parent.clone() {
-            Some(p) => p.clone(),
+    fn rotate_right(&mut self, node: usize) {
+        let parent = match self.node(node).parent {
+            Some(p) => p,
             None => {
-                self.root = Some(node.clone());
+                self.root = Some(node);
                 return;
             }
         };
-        let grand_parent = parent.borrow().parent.clone();
-        let rchild_of_node = node.borrow().rchild.clone();
+        self.record(|tree| trace::Step::RotateRight {
+            node: format!("{:?}", tree.node(node).data),
+            parent: format!("{:?}", tree.node(parent).data),
+        });
+        let grand_parent = self.node(parent).parent;
+        let rchild_of_node = self.node(node).rchild;
 
         // P<--C
-        if let Some(rc) = &rchild_of_node {
-            (*rc).borrow_mut().parent = Some(parent.clone());
+        if let Some(rc) = rchild_of_node {
+            self.node_mut(rc).parent = Some(parent);
         }
 
         // GP<-->X
-        if let Some(gp) = grand_parent.clone() {
-            match RBTreeNode::<T>::which_child(parent.clone()) {
-                Child::Left => (*gp).borrow_mut().lchild = Some(node.clone()),
-                Child::Right => (*gp).borrow_mut().rchild = Some(node.clone()),
+        if let Some(gp) = grand_parent {
+            match self.which_child(parent) {
+                Child::Left => self.node_mut(gp).lchild = Some(node),
+                Child::Right => self.node_mut(gp).rchild = Some(node),
                 _ => {}
             }
         } else {
-            self.root = Some(node.clone());
+            self.root = Some(node);
         }
-        (*node).borrow_mut().parent = grand_parent.clone();
+        self.node_mut(node).parent = grand_parent;
 
         // X<-->P-->C
-        (*parent).borrow_mut().parent = Some(node.clone());
-        (*parent).borrow_mut().lchild = rchild_of_node.clone();
-        (*node).borrow_mut().rchild = Some(parent.clone());
+        self.node_mut(parent).parent = Some(node);
+        self.node_mut(parent).lchild = rchild_of_node;
+        self.node_mut(node).rchild = Some(parent);
+
+        self.update_size(parent);
+        self.update_size(node);
     }
 
-    fn fix_insert(&mut self, node: Rc<RefCell<RBTreeNode<T>>>) {
-        if node.borrow().is_root() {
-            (*node).borrow_mut().color = Color::Black;
+    fn fix_insert(&mut self, node: usize) {
+        if self.node(node).is_root() {
+            self.set_color(node, Color::Black);
             return;
         }
 
-        let parent = node.borrow().parent.clone();
-        let uncle = node.borrow().get_uncle();
+        let parent = self.node(node).parent;
+        let uncle = self.get_uncle(node);
 
-        let (pcolor, ucolor) = match (&parent, &uncle) {
-            (Some(p), Some(u)) => (p.borrow().color, u.borrow().color),
-            (Some(p), None) => (p.borrow().color, Color::Black),
+        let (pcolor, ucolor) = match (parent, uncle) {
+            (Some(p), Some(u)) => (self.node(p).color, self.node(u).color),
+            (Some(p), None) => (self.node(p).color, Color::Black),
             (None, _) => return,
         };
 
         match (pcolor, ucolor) {
             // Recoloring (case1)
             (Color::Red, Color::Red) => {
+                self.record(|_| trace::Step::Case("red parent, red uncle"));
                 if let Some(u) = uncle {
-                    (*u).borrow_mut().color = Color::Black;
+                    self.set_color(u, Color::Black);
                 }
                 if let Some(p) = parent {
-                    (*p).borrow_mut().color = Color::Black;
-                    let pp = (*p).borrow().parent.clone();
-                    if let Some(gp) = pp {
-                        (*gp).borrow_mut().color = Color::Red;
-                        self.fix_insert(gp.clone());
+                    self.set_color(p, Color::Black);
+                    if let Some(gp) = self.node(p).parent {
+                        self.set_color(gp, Color::Red);
+                        self.fix_insert(gp);
                     }
                 }
             }
             // Rotating (case2)
             (Color::Red, Color::Black) => {
-                let new_node_dir = RBTreeNode::<T>::which_child(node.clone());
-                let parent_dir = if let Some(p) = &parent {
-                    RBTreeNode::<T>::which_child(p.clone())
+                let new_node_dir = self.which_child(node);
+                let parent_dir = if let Some(p) = parent {
+                    self.which_child(p)
                 } else {
                     return;
                 };
                 match (parent_dir, new_node_dir) {
                     // case2-1-1 (LL)
                     (Child::Left, Child::Left) => {
-                        if let Some(p) = &parent {
-                            if let Some(gp) = &(*p).borrow().parent {
-                                (*gp).borrow_mut().color = Color::Red;
+                        self.record(|_| trace::Step::Case("red parent, black uncle, outer child"));
+                        if let Some(p) = parent {
+                            if let Some(gp) = self.node(p).parent {
+                                self.set_color(gp, Color::Red);
                             }
-                            (*p).borrow_mut().color = Color::Black;
-                            self.rotate_right(p.clone());
+                            self.set_color(p, Color::Black);
+                            self.rotate_right(p);
                         }
                     }
                     // case2-1-2 (RR)
                     (Child::Right, Child::Right) => {
-                        if let Some(p) = &parent {
-                            if let Some(gp) = &(*p).borrow().parent {
-                                (*gp).borrow_mut().color = Color::Red;
+                        self.record(|_| trace::Step::Case("red parent, black uncle, outer child"));
+                        if let Some(p) = parent {
+                            if let Some(gp) = self.node(p).parent {
+                                self.set_color(gp, Color::Red);
                             }
-                            (*p).borrow_mut().color = Color::Black;
-                            self.rotate_left(p.clone());
+                            self.set_color(p, Color::Black);
+                            self.rotate_left(p);
                         }
                     }
                     // case2-2-1 (LR)
                     (Child::Left, Child::Right) => {
+                        self.record(|_| trace::Step::Case("red parent, black uncle, inner child"));
                         // make this case2-1-1 (LL)
-                        self.rotate_left(node.clone());
-                        if let Some(p) = &parent {
+                        self.rotate_left(node);
+                        if let Some(p) = parent {
                             // for case2-1-1 (LL)
-                            self.fix_insert(p.clone());
+                            self.fix_insert(p);
                         }
                     }
                     // case2-2-2 (RL)
                     (Child::Right, Child::Left) => {
+                        self.record(|_| trace::Step::Case("red parent, black uncle, inner child"));
                         // make this case2-1-2 (RR)
-                        self.rotate_right(node.clone());
-                        if let Some(p) = &parent {
+                        self.rotate_right(node);
+                        if let Some(p) = parent {
                             // for case2-1-2 (RR)
-                            self.fix_insert(p.clone());
+                            self.fix_insert(p);
                         }
                     }
                     (_, _) => {}
@@ -475,370 +743,1008 @@ impl<T: PartialOrd + Debug> RBTree<T> {
         }
     }
 
-    fn fix_delete(
-        &mut self,
-        starting: Option<Rc<RefCell<RBTreeNode<T>>>>,
-        parent: Option<Rc<RefCell<RBTreeNode<T>>>>,
-    ) {
-        let pnode = if let Some(_pnode) = parent.clone() {
+    fn fix_delete(&mut self, starting: Option<usize>, parent: Option<usize>) {
+        let pnode = if let Some(_pnode) = parent {
             _pnode
         } else {
             // 1) statring is a root node
-            if let Some(snode) = starting.clone() {
-                (*snode).borrow_mut().color = Color::Black;
+            if let Some(snode) = starting {
+                self.set_color(snode, Color::Black);
             }
-            self.root = starting.clone();
+            self.root = starting;
             return;
         };
 
         // 2) starting is not a root (parent exists)
 
         // 2-1) starting is a red node
-        if let Some(snode) = starting.clone() {
-            let color = snode.borrow().color;
-            if let Color::Red = color {
-                (*snode).borrow_mut().color = Color::Black;
+        if let Some(snode) = starting {
+            if let Color::Red = self.node(snode).color {
+                self.set_color(snode, Color::Black);
                 return;
             }
         }
 
         // 2-2) starting is a black node
-        let sibling = Self::get_sibling(starting.clone(), parent.clone());
-        if let Some(sibling_node) = &sibling {
+        let sibling = self.get_sibling(starting, parent);
+        if let Some(sibling_node) = sibling {
             // 2-2-1). sibling is red
-            let sibling_node_color = sibling_node.borrow().color;
-            if let Color::Red = sibling_node_color {
+            if let Color::Red = self.node(sibling_node).color {
+                self.record(|_| trace::Step::Case("red sibling"));
                 // 1. swap colors of sibling and parent (parent must be black)
-                (*sibling_node).borrow_mut().color = Color::Black;
-                (*pnode).borrow_mut().color = Color::Red;
+                self.set_color(sibling_node, Color::Black);
+                self.set_color(pnode, Color::Red);
 
                 // 2. rotate left or right
-                if let Child::Right = RBTreeNode::<T>::which_child(sibling_node.clone()) {
-                    self.rotate_left(sibling_node.clone());
+                if let Child::Right = self.which_child(sibling_node) {
+                    self.rotate_left(sibling_node);
                 } else {
-                    self.rotate_right(sibling_node.clone());
+                    self.rotate_right(sibling_node);
                 }
 
-                self.fix_delete(starting.clone(), parent.clone());
+                self.fix_delete(starting, parent);
                 return;
             }
 
             // 2-2-2). sibling is black
-            let close_child = Self::get_close_child_of_sibling(sibling_node.clone());
-            let far_child = Self::get_far_child_of_sibling(sibling_node.clone());
-            let close_color = Self::get_color(close_child.clone());
-            let far_color = Self::get_color(far_child.clone());
+            let close_child = self.get_close_child_of_sibling(sibling_node);
+            let far_child = self.get_far_child_of_sibling(sibling_node);
+            let close_color = self.get_color(close_child);
+            let far_color = self.get_color(far_child);
             match (far_color, close_color) {
                 // 1. children are black or Nil
                 (Color::Black, Color::Black) => {
+                    self.record(|_| trace::Step::Case("black sibling, black children"));
                     // 1-1. raise two blacks to parent
                     // raise extra black of starting node and sibling's black color to parent node, and
                     // recursively fix-up with new starting node which is parent of current starting node.
-                    (*sibling_node).borrow_mut().color = Color::Red;
+                    self.set_color(sibling_node, Color::Red);
                     // 1-2. fix-up with new starting node
-                    let ppnode = pnode.borrow().parent.clone();
-                    self.fix_delete(parent.clone(), ppnode);
+                    let ppnode = self.node(pnode).parent;
+                    self.fix_delete(parent, ppnode);
                 }
                 // 2. far child is red
                 (Color::Red, _) => {
+                    self.record(|_| trace::Step::Case("black sibling, red far child"));
                     // deliver sibling's black to 2 children
                     // swap parent & sibling color
                     // raise 2 blacks (starting, close child) to parent
                     let far_child_node = far_child.unwrap();
-                    (*sibling_node).borrow_mut().color = pnode.borrow().color;
-                    (*pnode).borrow_mut().color = Color::Black;
-                    (*far_child_node).borrow_mut().color = Color::Black;
-                    if let Child::Right = RBTreeNode::<T>::which_child(far_child_node.clone()) {
-                        self.rotate_left(sibling_node.clone());
+                    self.set_color(sibling_node, self.node(pnode).color);
+                    self.set_color(pnode, Color::Black);
+                    self.set_color(far_child_node, Color::Black);
+                    if let Child::Right = self.which_child(far_child_node) {
+                        self.rotate_left(sibling_node);
                     } else {
-                        self.rotate_right(sibling_node.clone());
+                        self.rotate_right(sibling_node);
                     }
                 }
                 // 3. close child is red (far child is black)
                 (Color::Black, Color::Red) => {
+                    self.record(|_| trace::Step::Case("black sibling, red close child"));
                     let close_child_node = close_child.unwrap();
                     // swap colors of sibling and close child
-                    (*sibling_node).borrow_mut().color = Color::Red;
-                    (*close_child_node).borrow_mut().color = Color::Black;
+                    self.set_color(sibling_node, Color::Red);
+                    self.set_color(close_child_node, Color::Black);
                     // rotate
-                    if let Child::Right = RBTreeNode::<T>::which_child(close_child_node.clone()) {
-                        self.rotate_left(close_child_node.clone());
+                    if let Child::Right = self.which_child(close_child_node) {
+                        self.rotate_left(close_child_node);
                     } else {
-                        self.rotate_right(close_child_node.clone());
+                        self.rotate_right(close_child_node);
                     }
                     // fix-up again (it will be case 3.)
-                    self.fix_delete(starting.clone(), parent.clone());
+                    self.fix_delete(starting, parent);
                 }
             }
         }
     }
 
-    fn find_node(&self, data: &T) -> Option<Rc<RefCell<RBTreeNode<T>>>> {
-        if let Some(root) = &self.root {
-            Self::_find_node(root.clone(), data)
-        } else {
-            None
-        }
+    fn find_node(&self, data: &T) -> Option<usize> {
+        self.find_node_by(|node_data| self.compare(node_data, data))
     }
 
-    fn _find_node(
-        current_node: Rc<RefCell<RBTreeNode<T>>>,
-        data: &T,
-    ) -> Option<Rc<RefCell<RBTreeNode<T>>>> {
-        match &current_node.borrow().data.partial_cmp(data) {
-            Some(res) => match res {
-                Ordering::Greater => {
-                    if let Some(lchild) = &current_node.borrow().lchild {
-                        Self::_find_node(lchild.clone(), data)
-                    } else {
-                        None
-                    }
-                }
-                Ordering::Less => {
-                    if let Some(rchild) = &current_node.borrow().rchild {
-                        Self::_find_node(rchild.clone(), data)
-                    } else {
-                        None
-                    }
-                }
-                Ordering::Equal => Some(current_node.clone()),
-            },
-            None => None,
+    // cmp tells how a node's data is ordered against the target
+    fn find_node_by(&self, cmp: impl Fn(&T) -> Option<Ordering>) -> Option<usize> {
+        let mut c = self.root;
+        while let Some(current) = c {
+            let current_node = self.node(current);
+            c = match cmp(&current_node.data)? {
+                Ordering::Greater => current_node.lchild,
+                Ordering::Less => current_node.rchild,
+                Ordering::Equal => return Some(current),
+            };
         }
+        None
     }
 
-    fn find_successor(
-        &self,
-        node: Rc<RefCell<RBTreeNode<T>>>,
-    ) -> Option<Rc<RefCell<RBTreeNode<T>>>> {
-        let right_child = node.borrow().rchild.clone();
-        if let Some(rchild) = &right_child {
-            let mut current_node = rchild.clone();
-            while let Some(lchild) = current_node.clone().borrow().lchild.clone() {
-                current_node = lchild;
-            }
-            Some(current_node)
-        } else {
-            None
-        }
+    pub(crate) fn get_by(&self, cmp: impl Fn(&T) -> Option<Ordering>) -> Option<&T> {
+        let node = self.find_node_by(cmp)?;
+        Some(&self.node(node).data)
+    }
+
+    // caller must not change how the data is ordered
+    pub(crate) fn get_mut_by(&mut self, cmp: impl Fn(&T) -> Option<Ordering>) -> Option<&mut T> {
+        let node = self.find_node_by(cmp)?;
+        Some(&mut self.node_mut(node).data)
+    }
+
+    fn find_successor(&self, node: usize) -> Option<usize> {
+        let rchild = self.node(node).rchild?;
+        Some(self.min_node(rchild))
     }
 
-    fn get_sibling(
-        me: Option<Rc<RefCell<RBTreeNode<T>>>>,
-        parent: Option<Rc<RefCell<RBTreeNode<T>>>>,
-    ) -> Option<Rc<RefCell<RBTreeNode<T>>>> {
+    fn get_sibling(&self, me: Option<usize>, parent: Option<usize>) -> Option<usize> {
         match (me, parent) {
             (Some(me_node), Some(pnode)) => {
-                if let Some(lchild) = pnode.borrow().lchild.clone() {
-                    if Rc::ptr_eq(&me_node, &lchild) {
-                        pnode.borrow().rchild.clone()
+                if let Some(lchild) = self.node(pnode).lchild {
+                    if me_node == lchild {
+                        self.node(pnode).rchild
                     } else {
-                        pnode.borrow().lchild.clone()
+                        self.node(pnode).lchild
                     }
                 } else {
-                    pnode.borrow().rchild.clone()
+                    self.node(pnode).rchild
                 }
             }
             (None, Some(pnode)) => {
-                if pnode.borrow().lchild.is_none() {
-                    pnode.borrow().rchild.clone()
+                if self.node(pnode).lchild.is_none() {
+                    self.node(pnode).rchild
                 } else {
-                    pnode.borrow().lchild.clone()
+                    self.node(pnode).lchild
                 }
             }
             _ => None,
         }
     }
 
-    fn get_far_child_of_sibling(
-        sibling: Rc<RefCell<RBTreeNode<T>>>,
-    ) -> Option<Rc<RefCell<RBTreeNode<T>>>> {
-        match RBTreeNode::<T>::which_child(sibling.clone()) {
-            Child::Left => sibling.borrow().lchild.clone(),
-            Child::Right => sibling.borrow().rchild.clone(),
+    fn get_far_child_of_sibling(&self, sibling: usize) -> Option<usize> {
+        match self.which_child(sibling) {
+            Child::Left => self.node(sibling).lchild,
+            Child::Right => self.node(sibling).rchild,
             Child::None => None,
         }
     }
 
-    fn get_close_child_of_sibling(
-        sibling: Rc<RefCell<RBTreeNode<T>>>,
-    ) -> Option<Rc<RefCell<RBTreeNode<T>>>> {
-        match RBTreeNode::<T>::which_child(sibling.clone()) {
-            Child::Left => sibling.borrow().rchild.clone(),
-            Child::Right => sibling.borrow().lchild.clone(),
+    fn get_close_child_of_sibling(&self, sibling: usize) -> Option<usize> {
+        match self.which_child(sibling) {
+            Child::Left => self.node(sibling).rchild,
+            Child::Right => self.node(sibling).lchild,
             Child::None => None,
         }
     }
 
-    fn get_color(node: Option<Rc<RefCell<RBTreeNode<T>>>>) -> Color {
-        if let Some(_node) = node.clone() {
-            _node.borrow().color
+    fn get_color(&self, node: Option<usize>) -> Color {
+        if let Some(_node) = node {
+            self.node(_node).color
         } else {
             Color::Black
         }
     }
 
-    fn transplant(
-        &mut self,
-        old_node: Rc<RefCell<RBTreeNode<T>>>,
-        new_node: Rc<RefCell<RBTreeNode<T>>>,
-        care_child_of_new: bool,
-    ) {
+    fn transplant(&mut self, old_node: usize, new_node: usize, care_child_of_new: bool) {
         if care_child_of_new {
-            let new_parent = new_node.borrow().parent.clone();
-            let new_rchild = new_node.borrow().rchild.clone();
+            let new_parent = self.node(new_node).parent;
+            let new_rchild = self.node(new_node).rchild;
             // new_node.rchild.parent = new_node.parent
-            if let Some(new_child) = &new_rchild {
-                (*new_child).borrow_mut().parent = new_parent.clone();
+            if let Some(new_child) = new_rchild {
+                self.node_mut(new_child).parent = new_parent;
             }
             // new_node.parent.lchild = new_node.rchild
-            if let Some(new_pnode) = &new_parent {
-                (*new_pnode).borrow_mut().lchild = new_rchild.clone();
+            if let Some(new_pnode) = new_parent {
+                self.node_mut(new_pnode).lchild = new_rchild;
             }
         }
 
-        let old_parent = old_node.borrow().parent.clone();
-        match RBTreeNode::<T>::which_child(old_node.clone()) {
+        let old_parent = self.node(old_node).parent;
+        match self.which_child(old_node) {
             Child::Left => {
                 // new_node.parent = old_node.parent
                 // old_node.parent.lchild = new_node
-                new_node.borrow_mut().parent = old_parent.clone();
-                old_parent.unwrap().borrow_mut().lchild = Some(new_node.clone());
+                self.node_mut(new_node).parent = old_parent;
+                self.node_mut(old_parent.unwrap()).lchild = Some(new_node);
             }
             Child::Right => {
                 // new_node.parent = old_node.parent
                 // old_node.parent.rchild = new_node
-                new_node.borrow_mut().parent = old_parent.clone();
-                old_parent.unwrap().borrow_mut().rchild = Some(new_node.clone());
+                self.node_mut(new_node).parent = old_parent;
+                self.node_mut(old_parent.unwrap()).rchild = Some(new_node);
             }
             Child::None => {
                 // self.root = new_node
-                new_node.borrow_mut().parent = None;
-                self.root = Some(new_node.clone());
+                self.node_mut(new_node).parent = None;
+                self.root = Some(new_node);
             }
         }
 
         // new_node.lchild = old_node.lchild
         // old_node.lchild.parent = new_node
-        let old_lchild = old_node.borrow().lchild.clone();
-        new_node.borrow_mut().lchild = old_lchild.clone();
-        if let Some(old_child) = &old_lchild {
-            (*old_child).borrow_mut().parent = Some(new_node.clone());
+        let old_lchild = self.node(old_node).lchild;
+        self.node_mut(new_node).lchild = old_lchild;
+        if let Some(old_child) = old_lchild {
+            self.node_mut(old_child).parent = Some(new_node);
         }
 
         // new_node.rchild = old_node.rchild
         // new_node.rchild.parent = new_node
         if care_child_of_new {
-            let old_rchild = old_node.borrow().rchild.clone();
-            new_node.borrow_mut().rchild = old_rchild.clone();
-            if let Some(rchild) = &old_rchild {
-                (*rchild).borrow_mut().parent = Some(new_node.clone());
-            }
-        }
-
-        // (*old_node).borrow_mut().lchild = None;
-        // (*old_node).borrow_mut().rchild = None;
-        // (*old_node).borrow_mut().parent = None;
-    }
-
-    fn _check(
-        node: Rc<RefCell<RBTreeNode<T>>>,
-        blacks: u32,
-        vec: &mut Vec<u32>,
-        depth: u32,
-        min_depth: &mut u32,
-        max_depth: &mut u32,
-    ) -> bool {
-        let lchild = node.borrow().lchild.clone();
-        let rchild = node.borrow().rchild.clone();
-        let mut res = true;
-        if let Some(left) = &lchild {
-            if left.borrow().data >= node.borrow().data {
-                return false;
-            }
-            if let Color::Black = left.borrow().color {
-                res = Self::_check(
-                    left.clone(),
-                    blacks + 1,
-                    vec,
-                    depth + 1,
-                    min_depth,
-                    max_depth,
-                );
-            } else {
-                res = Self::_check(left.clone(), blacks, vec, depth + 1, min_depth, max_depth);
+            let old_rchild = self.node(old_node).rchild;
+            self.node_mut(new_node).rchild = old_rchild;
+            if let Some(rchild) = old_rchild {
+                self.node_mut(rchild).parent = Some(new_node);
             }
-        } else {
-            vec.push(blacks);
-            if *min_depth > depth {
-                *min_depth = depth;
+        }
+    }
+
+    // walks the subtree of root depth first, with an explicit stack instead
+    // of recursion; returns the black height of root
+    fn validate_from(
+        &self,
+        root: usize,
+        stats: &mut TreeStats,
+        violations: &mut Vec<Violation>,
+    ) -> u32 {
+        let mut stack = vec![Visit {
+            node: root,
+            parent: None,
+            step: None,
+            range: (None, None),
+            depth: 1,
+            children_done: false,
+        }];
+        let mut results: Vec<(u32, usize)> = vec![]; // (black height, size) of subtrees done
+
+        while let Some(visit) = stack.pop() {
+            let node = visit.node;
+            let current = self.node(node);
+            // the ancestors are the nodes on the stack waiting for their children,
+            // so the path is only built when a violation is found
+            let step = visit.step;
+            let here = |stack: &Vec<Visit<T>>| {
+                let path = stack
+                    .iter()
+                    .filter(|v| v.children_done)
+                    .filter_map(|v| v.step);
+                (path.chain(step).collect(), format!("{:?}", current.data))
+            };
+
+            if !visit.children_done {
+                if let Some(parent) = visit.parent {
+                    if current.parent != Some(parent) {
+                        let (path, data) = here(&stack);
+                        violations.push(Violation::ParentLink { path, data });
+                    }
+                    if let (Color::Red, Color::Red) = (self.node(parent).color, current.color) {
+                        let (path, data) = here(&stack);
+                        violations.push(Violation::RedRed { path, data });
+                    }
+                }
+
+                let (low, high) = visit.range;
+                let too_low = low
+                    .is_some_and(|low| self.compare(&current.data, low) != Some(Ordering::Greater));
+                let too_high = high
+                    .is_some_and(|high| self.compare(&current.data, high) != Some(Ordering::Less));
+                if too_low || too_high {
+                    let (path, data) = here(&stack);
+                    violations.push(Violation::Order { path, data });
+                }
+
+                stack.push(Visit {
+                    children_done: true,
+                    ..visit
+                });
+                // left child on top, so it is done first
+                let children = [
+                    (current.rchild, 'R', (Some(&current.data), high)),
+                    (current.lchild, 'L', (low, Some(&current.data))),
+                ];
+                for (child, step, range) in children {
+                    let Some(child) = child else {
+                        stats.min_depth = stats.min_depth.min(visit.depth);
+                        stats.max_depth = stats.max_depth.max(visit.depth);
+                        continue;
+                    };
+                    stack.push(Visit {
+                        node: child,
+                        parent: Some(node),
+                        step: Some(step),
+                        range,
+                        depth: visit.depth + 1,
+                        children_done: false,
+                    });
+                }
+                continue;
             }
-            if *max_depth < depth {
-                *max_depth = depth;
+
+            // results of the children are on top, right above left
+            let mut black_heights = [0, 0];
+            let mut sizes = [0, 0];
+            for (i, child) in [(1, current.rchild), (0, current.lchild)] {
+                if child.is_some() {
+                    (black_heights[i], sizes[i]) = results.pop().unwrap();
+                }
             }
-        }
-        if let Some(right) = &rchild {
-            if right.borrow().data <= node.borrow().data {
-                return false;
+
+            if black_heights[0] != black_heights[1] {
+                let (path, data) = here(&stack);
+                violations.push(Violation::BlackHeight {
+                    path,
+                    data,
+                    left: black_heights[0],
+                    right: black_heights[1],
+                });
             }
-            if let Color::Black = right.borrow().color {
-                res = Self::_check(
-                    right.clone(),
-                    blacks + 1,
-                    vec,
-                    depth + 1,
-                    min_depth,
-                    max_depth,
-                );
-            } else {
-                res = Self::_check(right.clone(), blacks, vec, depth + 1, min_depth, max_depth);
+            let size = current.cnt + sizes[0] + sizes[1];
+            if current.size != size {
+                let (path, data) = here(&stack);
+                violations.push(Violation::Size {
+                    path,
+                    data,
+                    expected: size,
+                    found: current.size,
+                });
             }
-        } else {
-            vec.push(blacks);
-            if *min_depth > depth {
-                *min_depth = depth;
+
+            stats.nodes += 1;
+            stats.len += current.cnt;
+            let black = if let Color::Black = current.color {
+                1
+            } else {
+                0
+            };
+            results.push((black_heights[0] + black, size));
+        }
+        results.pop().map_or(0, |(black_height, _)| black_height)
+    }
+
+    // checks every rb-tree rule and bookkeeping of the tree
+    // and reports all broken ones
+    pub fn validate(&self) -> Result<TreeStats, Vec<Violation>> {
+        let mut stats = TreeStats {
+            len: 0,
+            nodes: 0,
+            black_height: 0,
+            min_depth: 0,
+            max_depth: 0,
+        };
+        let mut violations = vec![];
+        if let Some(root) = self.root {
+            if let Color::Red = self.node(root).color {
+                violations.push(Violation::RedRoot);
             }
-            if *max_depth < depth {
-                *max_depth = depth;
+            if self.node(root).parent.is_some() {
+                violations.push(Violation::ParentLink {
+                    path: String::new(),
+                    data: format!("{:?}", self.node(root).data),
+                });
             }
+            stats.min_depth = u32::MAX;
+            stats.black_height = self.validate_from(root, &mut stats, &mut violations);
+        }
+        if stats.len != self.cnt {
+            violations.push(Violation::CntTotal {
+                expected: stats.len,
+                found: self.cnt,
+            });
+        }
+
+        if violations.is_empty() {
+            Ok(stats)
+        } else {
+            Err(violations)
         }
-        res
     }
 
     // returns (rb-tree or not, black cnt, min_depth, max depth)
     pub fn check(&self) -> (bool, u32, u32, u32) {
-        let mut cnt = 0;
-        let mut max_depth = 0;
-        let mut min_depth = u32::MAX;
-        let mut vec = vec![];
-        if let Some(root) = self.root.clone() {
-            if !Self::_check(root, 1, &mut vec, 1, &mut min_depth, &mut max_depth) {
-                return (false, 0, 0, 0);
-            }
-            if !vec.is_empty() {
-                let a = vec[0];
-                for x in &vec[1..] {
-                    if a != *x {
-                        return (false, 0, 0, 0);
-                    }
-                }
-                cnt = a;
+        match self.validate() {
+            Ok(stats) => {
+                let TreeStats {
+                    black_height: cnt,
+                    min_depth,
+                    max_depth,
+                    ..
+                } = stats;
+                let is_rbt = cnt <= min_depth && cnt * 2 >= max_depth;
+                (is_rbt, cnt, min_depth, max_depth)
             }
+            Err(_) => (false, 0, 0, 0),
         }
+    }
 
-        let is_rbt = cnt <= min_depth && cnt * 2 >= max_depth;
-        (is_rbt, cnt, min_depth, max_depth)
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let slot_size = std::mem::size_of::<Option<RBTreeNode<T>>>();
+        let nodes = self.nodes.len() - self.free.len();
+        MemoryUsage {
+            nodes,
+            free_slots: self.free.len(),
+            slot_size,
+            used: nodes * slot_size,
+            allocated: self.nodes.capacity() * slot_size
+                + self.free.capacity() * std::mem::size_of::<usize>(),
+        }
     }
-}
 
-#[cfg(test)]
-mod test {
-    use crate::RBTree;
-    use rand::prelude::*;
-    #[test]
-    fn insertion_test() {
-        let mut rbt = RBTree::<f64>::new();
-        let mut rng = rand::thread_rng();
+    // gives the unused capacity of the arena back to the allocator
+    // (empty slots in the middle stay, for later inserts)
+    pub fn shrink_to_fit(&mut self) {
+        while let Some(None) = self.nodes.last() {
+            self.nodes.pop();
+        }
+        let len = self.nodes.len();
+        self.free.retain(|&slot| slot < len);
+        self.nodes.shrink_to_fit();
+        self.free.shrink_to_fit();
+    }
+
+    fn node(&self, node: usize) -> &RBTreeNode<T> {
+        self.nodes[node]
+            .as_ref()
+            .expect("linked slot must be occupied")
+    }
+
+    fn node_mut(&mut self, node: usize) -> &mut RBTreeNode<T> {
+        self.nodes[node]
+            .as_mut()
+            .expect("linked slot must be occupied")
+    }
+
+    // put a node into an empty slot (reused from the free list first)
+    fn alloc(&mut self, node: RBTreeNode<T>) -> usize {
+        if let Some(slot) = self.free.pop() {
+            self.nodes[slot] = Some(node);
+            slot
+        } else {
+            self.nodes.push(Some(node));
+            self.nodes.len() - 1
+        }
+    }
+
+    // take a node out of its slot, which goes to the free list
+    fn dealloc(&mut self, node: usize) -> RBTreeNode<T> {
+        let taken = self.nodes[node]
+            .take()
+            .expect("freed slot must be occupied");
+        if self.root.is_none() {
+            // every slot is empty now
+            self.nodes.clear();
+            self.free.clear();
+        } else {
+            self.free.push(node);
+        }
+        taken
+    }
+
+    // is node left child or right child of its parent
+    fn which_child(&self, node: usize) -> Child {
+        if let Some(parent) = self.node(node).parent {
+            if self.node(parent).lchild == Some(node) {
+                Child::Left
+            } else if self.node(parent).rchild == Some(node) {
+                Child::Right
+            } else {
+                Child::None
+            }
+        } else {
+            Child::None
+        }
+    }
+
+    fn get_childs_string(&self, node: usize) -> String {
+        let node = self.node(node);
+        let parent = match node.parent {
+            Some(var) => format!("{:?}", self.node(var).data),
+            None => "_".to_string(),
+        };
+        let lchild = match node.lchild {
+            Some(child) => format!("{:?}", self.node(child).data),
+            None => "_".to_string(),
+        };
+        let rchild = match node.rchild {
+            Some(child) => format!("{:?}", self.node(child).data),
+            None => "_".to_string(),
+        };
+        format!(
+            "[{:?}({:?}): (p{:?},l{:?},r{:?})]",
+            node.data, node.color, parent, lchild, rchild
+        )
+    }
+
+    // every node in pre-order, one per line
+    fn get_childs_string_chain(&self, node: usize) -> String {
+        let mut result = String::new();
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            result.push_str(self.get_childs_string(node).as_str());
+            result.push('\n');
+            stack.extend(self.node(node).rchild);
+            stack.extend(self.node(node).lchild);
+        }
+        result
+    }
+
+    fn get_size(&self, node: Option<usize>) -> usize {
+        if let Some(_node) = node {
+            self.node(_node).size
+        } else {
+            0
+        }
+    }
+
+    // recalculate size from cnt and children's sizes
+    fn update_size(&mut self, node: usize) {
+        let size = self.node(node).cnt
+            + self.get_size(self.node(node).lchild)
+            + self.get_size(self.node(node).rchild);
+        self.node_mut(node).size = size;
+
+        if let Some(augment) = self.augment {
+            // out of its slot for a while, to be changed next to its children
+            let mut current = self.nodes[node].take().unwrap();
+            augment(
+                &mut current.data,
+                current.lchild.map(|lc| &self.node(lc).data),
+                current.rchild.map(|rc| &self.node(rc).data),
+            );
+            self.nodes[node] = Some(current);
+        }
+    }
+
+    // recalculate sizes from node up to root
+    fn update_size_upward(&mut self, node: Option<usize>) {
+        let mut c = node;
+        while let Some(current) = c {
+            self.update_size(current);
+            c = self.node(current).parent;
+        }
+    }
+
+    // left-most node of the subtree
+    fn min_node(&self, node: usize) -> usize {
+        let mut current_node = node;
+        while let Some(lchild) = self.node(current_node).lchild {
+            current_node = lchild;
+        }
+        current_node
+    }
+
+    // right-most node of the subtree
+    fn max_node(&self, node: usize) -> usize {
+        let mut current_node = node;
+        while let Some(rchild) = self.node(current_node).rchild {
+            current_node = rchild;
+        }
+        current_node
+    }
+
+    // in-order next node (not limited to the right subtree)
+    fn next_node(&self, node: usize) -> Option<usize> {
+        if let Some(rchild) = self.node(node).rchild {
+            return Some(self.min_node(rchild));
+        }
+        let mut current_node = node;
+        loop {
+            let parent = self.node(current_node).parent?;
+            if let Child::Left = self.which_child(current_node) {
+                return Some(parent);
+            }
+            current_node = parent;
+        }
+    }
+
+    // in-order previous node (not limited to the left subtree)
+    fn prev_node(&self, node: usize) -> Option<usize> {
+        if let Some(lchild) = self.node(node).lchild {
+            return Some(self.max_node(lchild));
+        }
+        let mut current_node = node;
+        loop {
+            let parent = self.node(current_node).parent?;
+            if let Child::Right = self.which_child(current_node) {
+                return Some(parent);
+            }
+            current_node = parent;
+        }
+    }
+
+    // get parent's sibling
+    fn get_uncle(&self, node: usize) -> Option<usize> {
+        let parent = self.node(node).parent?;
+        let grand_parent = self.node(parent).parent?;
+        match self.which_child(parent) {
+            Child::Left => self.node(grand_parent).rchild,
+            Child::Right => self.node(grand_parent).lchild,
+            Child::None => None,
+        }
+    }
+}
+
+// a node on the stack of `validate_from`, visited before and after its children
+struct Visit<'a, T> {
+    node: usize,
+    parent: Option<usize>,
+    step: Option<char>, // 'L'/'R' from parent, None for the root
+    range: (Option<&'a T>, Option<&'a T>),
+    depth: u32,
+    children_done: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TreeStats {
+    pub len: usize,        // number of values (duplicates counted)
+    pub nodes: usize,      // number of distinct values
+    pub black_height: u32, // black nodes on every path from root to leaf
+    pub min_depth: u32,    // shallowest node missing a child
+    pub max_depth: u32,    // deepest node missing a child
+}
+
+// bytes held by a tree, from `RBTree::memory_usage`; heap data owned by the
+// values themselves (e.g. the bytes of a String) is not counted
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MemoryUsage {
+    pub nodes: usize,      // occupied slots
+    pub free_slots: usize, // empty slots kept for reuse
+    pub slot_size: usize,  // bytes per slot
+    pub used: usize,       // bytes of the occupied slots
+    pub allocated: usize,  // bytes reserved by the arena and its free list
+}
+
+// broken rule found by `RBTree::validate`
+// path: 'L'/'R' steps from root to the node ("" is root), data: node's value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    RedRoot,
+    RedRed {
+        path: String,
+        data: String,
+    },
+    BlackHeight {
+        path: String,
+        data: String,
+        left: u32,
+        right: u32,
+    },
+    Order {
+        path: String,
+        data: String,
+    },
+    ParentLink {
+        path: String,
+        data: String,
+    },
+    Size {
+        path: String,
+        data: String,
+        expected: usize,
+        found: usize,
+    },
+    CntTotal {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::RedRoot => write!(f, "root is red"),
+            Violation::RedRed { path, data } => {
+                write!(f, "red node {} at [{}] has a red parent", data, path)
+            }
+            Violation::BlackHeight {
+                path,
+                data,
+                left,
+                right,
+            } => write!(
+                f,
+                "node {} at [{}] has black height {} on the left but {} on the right",
+                data, path, left, right
+            ),
+            Violation::Order { path, data } => {
+                write!(f, "node {} at [{}] is out of order", data, path)
+            }
+            Violation::ParentLink { path, data } => {
+                write!(f, "node {} at [{}] has a broken parent link", data, path)
+            }
+            Violation::Size {
+                path,
+                data,
+                expected,
+                found,
+            } => write!(
+                f,
+                "node {} at [{}] has subtree size {} instead of {}",
+                data, path, found, expected
+            ),
+            Violation::CntTotal { expected, found } => {
+                write!(
+                    f,
+                    "tree counts {} values but nodes hold {}",
+                    found, expected
+                )
+            }
+        }
+    }
+}
+
+pub struct Iter<'a, T: Debug> {
+    tree: &'a RBTree<T>,
+    front: Option<usize>,
+    front_used: usize, // how many times `front` has been yielded
+    back: Option<usize>,
+    back_used: usize, // how many times `back` has been yielded
+    remaining: usize,
+}
+
+impl<'a, T: Debug> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        loop {
+            let node = self.tree.node(self.front?);
+            if self.front_used < node.cnt {
+                self.front_used += 1;
+                self.remaining -= 1;
+                return Some(&node.data);
+            }
+            self.front = self.tree.next_node(self.front?);
+            self.front_used = 0;
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: Debug> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        loop {
+            let node = self.tree.node(self.back?);
+            if self.back_used < node.cnt {
+                self.back_used += 1;
+                self.remaining -= 1;
+                return Some(&node.data);
+            }
+            self.back = self.tree.prev_node(self.back?);
+            self.back_used = 0;
+        }
+    }
+}
+
+impl<T: Debug> ExactSizeIterator for Iter<'_, T> {}
+impl<T: Debug> FusedIterator for Iter<'_, T> {}
+
+impl<'a, T: Debug> IntoIterator for &'a RBTree<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+// owned values with their multiplicity; duplicates are handed out as clones,
+// so only values stored more than once are cloned
+pub struct IntoIter<T> {
+    nodes: VecDeque<(T, usize)>,
+    remaining: usize,
+}
+
+impl<T: Clone> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let (data, cnt) = self.nodes.front_mut()?;
+        self.remaining -= 1;
+        if *cnt > 1 {
+            *cnt -= 1;
+            return Some(data.clone());
+        }
+        self.nodes.pop_front().map(|(data, _)| data)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T: Clone> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        let (data, cnt) = self.nodes.back_mut()?;
+        self.remaining -= 1;
+        if *cnt > 1 {
+            *cnt -= 1;
+            return Some(data.clone());
+        }
+        self.nodes.pop_back().map(|(data, _)| data)
+    }
+}
+
+impl<T: Clone> ExactSizeIterator for IntoIter<T> {}
+impl<T: Clone> FusedIterator for IntoIter<T> {}
+
+impl<T: Debug + Clone> IntoIterator for RBTree<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        let remaining = self.cnt;
+        IntoIter {
+            nodes: self.take_in_order(),
+            remaining,
+        }
+    }
+}
+
+// owned values once each with their counts, for values which can't be cloned
+pub struct IntoCounts<T> {
+    nodes: VecDeque<(T, usize)>,
+}
+
+impl<T> Iterator for IntoCounts<T> {
+    type Item = (T, usize);
+
+    fn next(&mut self) -> Option<(T, usize)> {
+        self.nodes.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.nodes.len(), Some(self.nodes.len()))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoCounts<T> {
+    fn next_back(&mut self) -> Option<(T, usize)> {
+        self.nodes.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoCounts<T> {}
+impl<T> FusedIterator for IntoCounts<T> {}
+
+impl<T: Debug> RBTree<T> {
+    pub fn into_counts(self) -> IntoCounts<T> {
+        IntoCounts {
+            nodes: self.take_in_order(),
+        }
+    }
+
+    // moves every node's value out of the arena, in order
+    fn take_in_order(mut self) -> VecDeque<(T, usize)> {
+        let mut order = vec![];
+        let mut c = self.root.map(|root| self.min_node(root));
+        while let Some(current) = c {
+            order.push(current);
+            c = self.next_node(current);
+        }
+
+        order
+            .into_iter()
+            .filter_map(|slot| self.nodes[slot].take())
+            .map(|node| (node.data, node.cnt))
+            .collect()
+    }
+}
+
+// read-only view of a node, to walk the shape of a tree
+pub struct NodeRef<'a, T: Debug> {
+    tree: &'a RBTree<T>,
+    node: usize,
+}
+
+impl<T: Debug> Clone for NodeRef<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Debug> Copy for NodeRef<'_, T> {}
+
+impl<'a, T: Debug> NodeRef<'a, T> {
+    pub fn data(&self) -> &'a T {
+        &self.tree.node(self.node).data
+    }
+
+    // copies of the value
+    pub fn count(&self) -> usize {
+        self.tree.node(self.node).cnt
+    }
+
+    pub fn color(&self) -> Color {
+        self.tree.node(self.node).color
+    }
+
+    pub fn left(&self) -> Option<NodeRef<'a, T>> {
+        self.tree.node_ref(self.tree.node(self.node).lchild)
+    }
+
+    pub fn right(&self) -> Option<NodeRef<'a, T>> {
+        self.tree.node_ref(self.tree.node(self.node).rchild)
+    }
+}
+
+impl<T: Debug> RBTree<T> {
+    pub fn root_node(&self) -> Option<NodeRef<'_, T>> {
+        self.node_ref(self.root)
+    }
+
+    fn node_ref(&self, node: Option<usize>) -> Option<NodeRef<'_, T>> {
+        node.map(|node| NodeRef { tree: self, node })
+    }
+}
+
+impl<T: PartialOrd + Debug> Default for RBTree<T> {
+    fn default() -> Self {
+        RBTree::new()
+    }
+}
+
+impl<T: Debug + Clone> Clone for RBTree<T> {
+    fn clone(&self) -> Self {
+        RBTree {
+            nodes: self.nodes.clone(),
+            free: self.free.clone(),
+            root: self.root,
+            cnt: self.cnt,
+            cmp: self.cmp.clone(),
+            augment: self.augment,
+            trace: None,
+        }
+    }
+}
+
+// equal when they hold the same values in the same order, whatever the shape
+impl<T: Debug + PartialEq> PartialEq for RBTree<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Debug + Eq> Eq for RBTree<T> {}
+
+// values which are not comparable (e.g. NaN) are skipped, as by `insert`
+impl<T: Debug> Extend<T> for RBTree<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for data in iter {
+            self.insert(data);
+        }
+    }
+}
+
+impl<'a, T: Debug + Copy + 'a> Extend<&'a T> for RBTree<T> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<T: PartialOrd + Debug> FromIterator<T> for RBTree<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = RBTree::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model_test::test_rng,
+        tree::{Color, RBTree, Violation},
+    };
+    use rand::prelude::*;
+    use std::ops::Bound;
+    #[test]
+    fn insertion_test() {
+        let mut rbt = RBTree::<f64>::new();
+        let mut rng = test_rng();
         let mut nums: Vec<f64> = vec![];
         for _ in 0..1000000 {
             nums.push(rng.gen());
@@ -855,7 +1761,7 @@ mod test {
     #[test]
     fn find_test() {
         let mut rbt = RBTree::<u64>::new();
-        let mut rng = rand::thread_rng();
+        let mut rng = test_rng();
         let (min, max) = (0, 1000000);
         let mut nums: Vec<u64> = (min..max).collect();
         nums.shuffle(&mut rng);
@@ -875,15 +1781,15 @@ mod test {
 
         let num = 11;
         let found_node = rbt.find_node(&num);
-        let test_result = if let Some(found) = &found_node {
-            num == found.borrow().data
+        let test_result = if let Some(found) = found_node {
+            num == rbt.node(found).data
         } else {
             false
         };
         assert!(test_result);
 
         let test_result = if let Some(found) = rbt.find_successor(found_node.unwrap()) {
-            found.borrow().data == 12
+            rbt.node(found).data == 12
         } else {
             false
         };
@@ -892,7 +1798,7 @@ mod test {
     #[test]
     fn deletion_test() {
         let mut rbt = RBTree::<u64>::new();
-        let mut rng = rand::thread_rng();
+        let mut rng = test_rng();
         let mut nums: Vec<u64> = vec![];
         for _ in 0..10000 {
             let n = rng.gen();
@@ -919,7 +1825,7 @@ mod test {
     fn insert_delete_test() {
         let mut rbt = RBTree::<u64>::new();
         let mut backup: Vec<u64> = vec![];
-        let mut rng = rand::thread_rng();
+        let mut rng = test_rng();
         let mut insert_cnt = 0;
         let mut delete_cnt = 0;
         for _ in 0..10000 {
@@ -949,7 +1855,7 @@ mod test {
     #[test]
     fn insert_delete_dup_test() {
         let mut rbt = RBTree::<f64>::new();
-        let mut rng = rand::thread_rng();
+        let mut rng = test_rng();
         let mut nums: Vec<f64> = vec![];
         for _ in 0..10000 {
             nums.push(rng.gen());
@@ -980,6 +1886,320 @@ mod test {
             assert!(blacks * 2 >= max_depth);
         }
     }
+    #[test]
+    fn iter_test() {
+        let mut rbt = RBTree::<u64>::new();
+        let mut rng = test_rng();
+        let mut nums: Vec<u64> = vec![];
+        for _ in 0..10000 {
+            let n = rng.gen_range(0..1000);
+            rbt.insert(n);
+            nums.push(n);
+        }
+        nums.sort();
+
+        assert_eq!(rbt.iter().len(), nums.len());
+        assert!(rbt.iter().eq(nums.iter()));
+        assert!(rbt.iter().rev().eq(nums.iter().rev()));
+        assert!((&rbt).into_iter().eq(nums.iter()));
+
+        // both ends meet in the middle of a duplicated value
+        let mut rbt = RBTree::<u64>::new();
+        for n in [1, 2, 2, 2, 3] {
+            rbt.insert(n);
+        }
+        let mut iter = rbt.iter();
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next_back(), Some(&3));
+        assert_eq!(iter.next_back(), Some(&2));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+    }
+    #[test]
+    fn range_test() {
+        let mut rbt = RBTree::<u64>::new();
+        let mut rng = test_rng();
+        let mut nums: Vec<u64> = vec![];
+        for _ in 0..10000 {
+            let n = rng.gen_range(0..1000);
+            rbt.insert(n);
+            nums.push(n);
+            if rng.gen_bool(0.3) {
+                let m = nums.swap_remove(rng.gen_range(0..nums.len()));
+                rbt.delete(&m);
+            }
+        }
+        nums.sort();
+        let (is_rbt, _, _, _) = rbt.check();
+        assert!(is_rbt);
+
+        for _ in 0..100 {
+            let a = rng.gen_range(0..1100);
+            let b = rng.gen_range(0..1100);
+            let expected: Vec<&u64> = nums.iter().filter(|&&n| a <= n && n < b).collect();
+            assert_eq!(rbt.count_range(a..b), expected.len());
+            assert!(rbt.range(a..b).eq(expected.iter().cloned()));
+            assert!(rbt.range(a..b).rev().eq(expected.iter().rev().cloned()));
+
+            let expected: Vec<&u64> = nums.iter().filter(|&&n| a < n && n <= b).collect();
+            let bounds = (Bound::Excluded(a), Bound::Included(b));
+            assert_eq!(rbt.count_range(bounds), expected.len());
+            assert!(rbt.range(bounds).eq(expected.iter().cloned()));
+        }
+        assert_eq!(rbt.count_range(..), nums.len());
+        assert!(rbt.range(..).eq(nums.iter()));
+        assert!(rbt.range(500..).eq(nums.iter().filter(|&&n| n >= 500)));
+        assert!(rbt.range(..=500).eq(nums.iter().filter(|&&n| n <= 500)));
+    }
+    #[test]
+    fn select_rank_test() {
+        let mut rbt = RBTree::<u64>::new();
+        let mut rng = test_rng();
+        let mut nums: Vec<u64> = vec![];
+        for _ in 0..10000 {
+            let n = rng.gen_range(0..1000);
+            rbt.insert(n);
+            nums.push(n);
+            if rng.gen_bool(0.3) {
+                let m = nums.swap_remove(rng.gen_range(0..nums.len()));
+                rbt.delete(&m);
+            }
+        }
+        nums.sort();
+
+        for (k, n) in nums.iter().enumerate() {
+            assert_eq!(rbt.select(k), Some(n));
+        }
+        assert_eq!(rbt.select(nums.len()), None);
+        for n in 0..1001 {
+            assert_eq!(rbt.rank(&n), nums.partition_point(|&m| m < n));
+        }
+        assert_eq!(rbt.select(nums.len() / 2), Some(&nums[nums.len() / 2]));
+    }
+    #[test]
+    fn bound_query_test() {
+        let mut rbt = RBTree::<u64>::new();
+        assert_eq!(rbt.min(), None);
+        assert_eq!(rbt.max(), None);
+        assert_eq!(rbt.floor(&0), None);
+
+        let mut rng = test_rng();
+        let mut nums: Vec<u64> = vec![];
+        for _ in 0..1000 {
+            let n = rng.gen_range(0..10000) * 2;
+            rbt.insert(n);
+            nums.push(n);
+        }
+        nums.sort();
+
+        assert_eq!(rbt.min(), nums.first());
+        assert_eq!(rbt.max(), nums.last());
+        for n in 0..20002 {
+            let le = nums.iter().rev().find(|&&m| m <= n);
+            let lt = nums.iter().rev().find(|&&m| m < n);
+            let ge = nums.iter().find(|&&m| m >= n);
+            let gt = nums.iter().find(|&&m| m > n);
+            assert_eq!(rbt.floor(&n), le);
+            assert_eq!(rbt.predecessor(&n), lt);
+            assert_eq!(rbt.ceiling(&n), ge);
+            assert_eq!(rbt.lower_bound(&n), ge);
+            assert_eq!(rbt.successor(&n), gt);
+            assert_eq!(rbt.upper_bound(&n), gt);
+        }
+    }
+    #[test]
+    fn into_iter_test() {
+        let mut rbt = RBTree::<u64>::new();
+        let mut rng = test_rng();
+        let mut nums: Vec<u64> = vec![];
+        for _ in 0..10000 {
+            let n = rng.gen_range(0..1000);
+            rbt.insert(n);
+            nums.push(n);
+        }
+        nums.sort();
+
+        let rev: Vec<u64> = nums.iter().rev().cloned().collect();
+        let mut rbt2 = RBTree::<u64>::new();
+        for n in &nums {
+            rbt2.insert(*n);
+        }
+        assert!(rbt.into_iter().eq(nums.into_iter()));
+        assert!(rbt2.into_iter().rev().eq(rev.into_iter()));
+        assert_eq!(RBTree::<u64>::new().into_iter().next(), None);
+
+        // values which can't be cloned come out once with their counts
+        #[derive(Debug, PartialEq, PartialOrd)]
+        struct Token(u32);
+        let mut tokens = RBTree::<Token>::new();
+        for n in [3, 1, 3, 2] {
+            tokens.insert(Token(n));
+        }
+        let counts: Vec<(Token, usize)> = tokens.into_counts().rev().collect();
+        assert_eq!(counts, [(Token(3), 2), (Token(2), 1), (Token(1), 1)]);
+    }
+    #[test]
+    fn validate_test() {
+        let mut rbt = RBTree::<u64>::new();
+        assert!(rbt.validate().is_ok());
+        for n in 0..100 {
+            rbt.insert(n);
+        }
+        rbt.insert(50);
+        let stats = rbt.validate().unwrap();
+        assert_eq!(stats.len, 101);
+        assert_eq!(stats.nodes, 100);
+        assert_eq!(
+            rbt.check(),
+            (true, stats.black_height, stats.min_depth, stats.max_depth)
+        );
+
+        let root = rbt.root.unwrap();
+        rbt.node_mut(root).color = Color::Red;
+        assert!(rbt.validate().unwrap_err().contains(&Violation::RedRoot));
+        rbt.node_mut(root).color = Color::Black;
+
+        rbt.cnt += 1;
+        assert_eq!(
+            rbt.validate().unwrap_err(),
+            vec![Violation::CntTotal {
+                expected: 101,
+                found: 102
+            }]
+        );
+        rbt.cnt -= 1;
+
+        // swap values of root and its left child
+        let left = rbt.node(root).lchild.unwrap();
+        let root_data = rbt.node(root).data;
+        rbt.node_mut(root).data = rbt.node(left).data;
+        rbt.node_mut(left).data = root_data;
+        let violations = rbt.validate().unwrap_err();
+        assert!(violations.contains(&Violation::Order {
+            path: "L".to_string(),
+            data: format!("{}", root_data),
+        }));
+        rbt.node_mut(left).data = rbt.node(root).data;
+        rbt.node_mut(root).data = root_data;
+
+        rbt.node_mut(left).parent = None;
+        assert!(matches!(
+            rbt.validate().unwrap_err()[0],
+            Violation::ParentLink { .. }
+        ));
+        rbt.node_mut(left).parent = Some(root);
+
+        // recolor a black node on a single path
+        let leftmost = rbt.min_node(root);
+        let color = rbt.node(leftmost).color;
+        rbt.node_mut(leftmost).color = match color {
+            Color::Red => Color::Black,
+            Color::Black => Color::Red,
+        };
+        let violations = rbt.validate().unwrap_err();
+        assert!(violations
+            .iter()
+            .any(|v| matches!(v, Violation::BlackHeight { .. })));
+        assert!(violations
+            .iter()
+            .any(|v| v.to_string().contains("has black height")));
+    }
+    #[test]
+    fn comparator_test() {
+        let mut rbt = RBTree::<f64>::new();
+        // refused even with nothing to compare it with
+        assert!(!rbt.insert(f64::NAN));
+        assert!(rbt.is_empty());
+        assert!(rbt.insert(1.0));
+        assert!(!rbt.insert(f64::NAN));
+        let err = rbt.try_insert(f64::NAN).unwrap_err();
+        assert!(err.data.is_nan());
+        assert_eq!(
+            err.to_string(),
+            "NaN is not comparable with values in the tree"
+        );
+        assert_eq!(rbt.len(), 1);
+
+        let mut rbt = RBTree::<u64>::new_ord();
+        for n in [3, 1, 2] {
+            assert!(rbt.try_insert(n).is_ok());
+        }
+        assert!(rbt.iter().eq([1, 2, 3].iter()));
+
+        // reverse order
+        let mut rbt = RBTree::<u64>::with_comparator(|a, b| b.cmp(a));
+        for n in 0..1000 {
+            rbt.insert(n);
+        }
+        assert!(rbt.check().0);
+        assert!(rbt.iter().eq((0..1000).rev().collect::<Vec<u64>>().iter()));
+        assert_eq!(rbt.min(), Some(&999));
+        assert_eq!(rbt.floor(&500), Some(&500));
+        assert_eq!(rbt.predecessor(&500), Some(&501));
+        assert_eq!(
+            rbt.count_range((Bound::Included(600), Bound::Excluded(400))),
+            200
+        );
+
+        // case-insensitive strings
+        let mut rbt =
+            RBTree::<String>::with_comparator(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
+        for word in ["Banana", "apple", "APPLE", "cherry", "Apple"] {
+            rbt.insert(word.to_string());
+        }
+        assert!(rbt.find(&"BANANA".to_string()));
+        assert_eq!(rbt.len(), 5);
+        assert_eq!(rbt.count_range(.."b".to_string()), 3);
+        assert!(rbt.delete(&"aPPle".to_string()));
+        assert_eq!(rbt.count_range(.."b".to_string()), 2);
+
+        // derived key of a type without PartialOrd
+        #[derive(Debug)]
+        struct Task {
+            priority: u32,
+            name: &'static str,
+        }
+        let mut rbt = RBTree::with_comparator(|a: &Task, b: &Task| b.priority.cmp(&a.priority));
+        rbt.insert(Task {
+            priority: 1,
+            name: "low",
+        });
+        rbt.insert(Task {
+            priority: 9,
+            name: "urgent",
+        });
+        rbt.insert(Task {
+            priority: 5,
+            name: "normal",
+        });
+        let names: Vec<&str> = rbt.iter().map(|task| task.name).collect();
+        assert_eq!(names, ["urgent", "normal", "low"]);
+    }
+    #[test]
+    fn slot_reuse_test() {
+        let mut rbt = RBTree::<u64>::new();
+        for n in 0..1000 {
+            rbt.insert(n);
+        }
+        for n in 0..500 {
+            rbt.delete(&n);
+        }
+        assert_eq!(rbt.free.len(), 500);
+        for n in 1000..1500 {
+            rbt.insert(n);
+        }
+        assert!(rbt.free.is_empty());
+        assert_eq!(rbt.nodes.len(), 1000);
+        assert!(rbt.iter().eq((500..1500).collect::<Vec<u64>>().iter()));
+
+        for n in 500..1500 {
+            rbt.delete(&n);
+        }
+        assert!(rbt.nodes.is_empty());
+        assert!(rbt.free.is_empty());
+    }
     // #[test]
     // fn delete_custom_test() {
     //     let mut rbt = RBTree::<u64>::new();
@@ -1002,4 +2222,71 @@ mod test {
     //     let (is_rbt, _, _, _) = rbt.check();
     //     assert!(is_rbt);
     // }
+    #[test]
+    fn std_traits_test() {
+        let mut rbt: RBTree<u64> = [5, 1, 3, 3].into_iter().collect();
+        assert_eq!(rbt, RBTree::from_sorted_iter([1, 3, 3, 5]));
+        assert_eq!(RBTree::<u64>::default(), RBTree::new());
+
+        let copy = rbt.clone();
+        rbt.extend(&[2, 4]);
+        rbt.extend(vec![6]);
+        assert!(rbt.iter().eq([1, 2, 3, 3, 4, 5, 6].iter()));
+        assert!(rbt.validate().is_ok());
+        assert!(copy.iter().eq([1, 3, 3, 5].iter()));
+        assert!(copy.validate().is_ok());
+        assert_ne!(rbt, copy);
+
+        let floats: RBTree<f64> = [1.0, f64::NAN, 2.0].into_iter().collect();
+        assert_eq!(floats.len(), 2);
+
+        let root = copy.root_node().unwrap();
+        assert_eq!((*root.data(), root.color()), (3, Color::Black));
+        assert_eq!(root.count(), 2);
+        let children = [root.left(), root.right()].map(|child| child.map(|c| *c.data()));
+        assert_eq!(children, [Some(1), Some(5)]);
+        assert!(RBTree::<u64>::new().root_node().is_none());
+    }
+    #[test]
+    fn drop_test() {
+        // a million nodes checked, printed and dropped on a thread with a small stack
+        std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(|| {
+                let rbt = RBTree::<u64>::from_sorted_iter(0..1_000_000);
+                assert!(rbt.check().0);
+                assert_eq!(format!("{:?}", rbt).lines().count(), 1_000_001);
+                drop(rbt);
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+    #[test]
+    fn memory_usage_test() {
+        let mut rbt = RBTree::<u64>::new();
+        assert_eq!(rbt.memory_usage().allocated, 0);
+        for n in 0..1000 {
+            rbt.insert(n);
+        }
+        let usage = rbt.memory_usage();
+        assert_eq!((usage.nodes, usage.free_slots), (1000, 0));
+        assert_eq!(usage.used, 1000 * usage.slot_size);
+        assert!(usage.allocated >= usage.used);
+
+        for n in 500..1000 {
+            rbt.delete(&n);
+        }
+        let usage = rbt.memory_usage();
+        assert_eq!((usage.nodes, usage.free_slots), (500, 500));
+        rbt.shrink_to_fit();
+        let shrunk = rbt.memory_usage();
+        assert_eq!(shrunk.nodes, 500);
+        assert!(shrunk.allocated < usage.allocated);
+        assert!(rbt.validate().is_ok());
+        for n in 500..1000 {
+            rbt.insert(n);
+        }
+        assert!(rbt.iter().copied().eq(0..1000));
+    }
 }
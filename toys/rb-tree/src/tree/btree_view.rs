@@ -0,0 +1,227 @@
+use super::{Color, RBTree};
+use std::fmt::{Debug, Write};
+
+// node of the 2-3-4 tree which a red-black tree stands for: a black node
+// with its red children merged into it
+pub struct BTreeNode<'a, T> {
+    pub keys: Vec<(&'a T, usize)>, // values with their counts, ascending
+    pub children: Vec<BTreeNode<'a, T>>, // one more than keys, none in a leaf
+}
+
+impl<T: Debug> BTreeNode<'_, T> {
+    fn label(&self) -> Vec<String> {
+        self.keys
+            .iter()
+            .map(|(data, cnt)| match cnt {
+                1 => format!("{:?}", data),
+                _ => format!("{:?} x{}", data, cnt),
+            })
+            .collect()
+    }
+}
+
+// escapes a field of a Graphviz record label
+fn record_field(label: &str) -> String {
+    let mut field = String::new();
+    for c in label.chars() {
+        if "\\\"|{}<> ".contains(c) {
+            field.push('\\');
+        }
+        field.push(c);
+    }
+    field
+}
+
+impl<T: Debug> RBTree<T> {
+    pub fn to_btree(&self) -> Option<BTreeNode<'_, T>> {
+        self.root.map(|root| self.btree_node(root))
+    }
+
+    fn btree_node(&self, node: usize) -> BTreeNode<'_, T> {
+        let mut keys = vec![];
+        let mut children = vec![];
+        let push_child = |child: Option<usize>, children: &mut Vec<_>| {
+            if let Some(child) = child {
+                children.push(self.btree_node(child));
+            }
+        };
+        let current = self.node(node);
+        for (i, child) in [current.lchild, current.rchild].into_iter().enumerate() {
+            match child.map(|child| self.node(child)) {
+                Some(red) if red.color == Color::Red => {
+                    push_child(red.lchild, &mut children);
+                    keys.push((&red.data, red.cnt));
+                    push_child(red.rchild, &mut children);
+                }
+                _ => push_child(child, &mut children),
+            }
+            if i == 0 {
+                keys.push((&current.data, current.cnt));
+            }
+        }
+        BTreeNode { keys, children }
+    }
+
+    // the 2-3-4 tree top down, children in ascending order
+    // [2 | 4]
+    // |-- [1]
+    // |-- [3]
+    // \-- [5 | 6]
+    pub fn to_btree_ascii_art(&self) -> String {
+        let mut art = String::new();
+        if let Some(root) = self.to_btree() {
+            write_btree_node(&root, "", "", &mut art);
+        }
+        art
+    }
+
+    // `to_ascii_art` and `to_btree_ascii_art` in two columns
+    pub fn to_side_by_side(&self) -> String {
+        let left = self.to_ascii_art();
+        let right = self.to_btree_ascii_art();
+        let width = left
+            .lines()
+            .map(|line| line.chars().count())
+            .chain(["red-black".len()])
+            .max()
+            .unwrap_or(0);
+        let mut art = format!("{:width$}    2-3-4\n", "red-black");
+        let (mut left, mut right) = (left.lines(), right.lines());
+        loop {
+            match (left.next(), right.next()) {
+                (None, None) => break,
+                (l, r) => {
+                    let line = format!("{:width$}    {}", l.unwrap_or(""), r.unwrap_or(""));
+                    art.push_str(line.trim_end());
+                    art.push('\n');
+                }
+            }
+        }
+        art
+    }
+
+    // `to_dot` and the 2-3-4 tree as records, side by side in one graph
+    pub fn to_dot_with_btree(&self) -> String {
+        let mut dot = String::from("digraph RBTree {\n");
+        dot.push_str("    subgraph cluster_rb {\n        label=\"red-black\";\n");
+        dot.push_str("        node [shape=circle, style=filled, fontcolor=white];\n");
+        let mut rb = String::new();
+        let mut nils = 0;
+        if let Some(root) = self.root {
+            self.write_dot_node(root, &mut rb, &mut nils);
+        }
+        for line in rb.lines() {
+            let _ = writeln!(dot, "    {}", line);
+        }
+        dot.push_str("    }\n");
+
+        dot.push_str("    subgraph cluster_btree {\n        label=\"2-3-4\";\n");
+        dot.push_str("        node [shape=record];\n");
+        if let Some(root) = self.to_btree() {
+            write_btree_dot(&root, &mut 0, &mut dot);
+        }
+        dot.push_str("    }\n}\n");
+        dot
+    }
+}
+
+fn write_btree_node<T: Debug>(
+    node: &BTreeNode<T>,
+    prefix: &str,
+    connector: &str,
+    art: &mut String,
+) {
+    let _ = writeln!(art, "{}{}[{}]", prefix, connector, node.label().join(" | "));
+    let prefix = match connector {
+        "|-- " => format!("{}|   ", prefix),
+        "\\-- " => format!("{}    ", prefix),
+        _ => prefix.to_string(),
+    };
+    for (i, child) in node.children.iter().enumerate() {
+        let connector = if i + 1 == node.children.len() {
+            "\\-- "
+        } else {
+            "|-- "
+        };
+        write_btree_node(child, &prefix, connector, art);
+    }
+}
+
+// writes node as b<id> with a port p<i> before each key, and returns its id
+fn write_btree_dot<T: Debug>(node: &BTreeNode<T>, next_id: &mut usize, dot: &mut String) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+    let mut fields = vec!["<p0>".to_string()];
+    for (i, key) in node.label().iter().enumerate() {
+        fields.push(record_field(key));
+        fields.push(format!("<p{}>", i + 1));
+    }
+    let _ = writeln!(dot, "        b{} [label=\"{}\"];", id, fields.join("|"));
+    for (i, child) in node.children.iter().enumerate() {
+        let child_id = write_btree_dot(child, next_id, dot);
+        let _ = writeln!(dot, "        b{}:p{} -> b{};", id, i, child_id);
+    }
+    id
+}
+
+#[cfg(test)]
+mod test {
+    use crate::RBTree;
+    #[test]
+    fn btree_test() {
+        let mut rbt = RBTree::<u64>::new();
+        assert!(rbt.to_btree().is_none());
+        assert_eq!(rbt.to_btree_ascii_art(), "");
+        for n in 1..=6 {
+            rbt.insert(n);
+        }
+        rbt.insert(1);
+        let root = rbt.to_btree().unwrap();
+        assert_eq!(root.keys, [(&2, 1), (&4, 1)]);
+        assert_eq!(root.children.len(), 3);
+        assert_eq!(
+            rbt.to_btree_ascii_art(),
+            "[2 | 4]\n|-- [1 x2]\n|-- [3]\n\\-- [5 | 6]\n"
+        );
+        assert_eq!(
+            rbt.to_side_by_side(),
+            [
+                "red-black           2-3-4",
+                "        /-- 6(R)    [2 | 4]",
+                "    /-- 5(B)        |-- [1 x2]",
+                "/-- 4(R)            |-- [3]",
+                "|   \\-- 3(B)        \\-- [5 | 6]",
+                "2(B)",
+                "\\-- 1(B) x2",
+                "",
+            ]
+            .join("\n")
+        );
+
+        // every leaf of the 2-3-4 tree is at the same depth
+        for n in 7..200 {
+            rbt.insert(n);
+        }
+        let mut level = vec![rbt.to_btree().unwrap()];
+        while !level[0].children.is_empty() {
+            assert!(level
+                .iter()
+                .all(|node| node.children.len() == node.keys.len() + 1));
+            level = level.into_iter().flat_map(|node| node.children).collect();
+        }
+        assert!(level.iter().all(|node| node.children.is_empty()));
+    }
+    #[test]
+    fn dot_with_btree_test() {
+        let mut rbt = RBTree::<u64>::new();
+        for n in [2, 1, 3, 3, 4] {
+            rbt.insert(n);
+        }
+        let dot = rbt.to_dot_with_btree();
+        assert!(dot.contains("subgraph cluster_rb {"));
+        assert!(dot.contains("        n0 [label=\"2\", fillcolor=black];"));
+        assert!(dot.contains("        b0 [label=\"<p0>|2|<p1>\"];"));
+        assert!(dot.contains("        b2 [label=\"<p0>|3\\ x2|<p1>|4|<p2>\"];"));
+        assert!(dot.contains("        b0:p1 -> b2;"));
+    }
+}
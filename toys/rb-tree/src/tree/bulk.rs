@@ -0,0 +1,339 @@
+use super::{Color, IntoIter, Place, RBTree, RBTreeNode};
+use std::{cmp::Ordering, fmt::Debug, ops::RangeBounds};
+
+impl<T: PartialOrd + Debug> RBTree<T> {
+    // builds in O(n) from values in ascending order
+    // (values out of order are still placed right, by plain insertion, and
+    // values which are not comparable, e.g. NaN, are skipped)
+    pub fn from_sorted_iter(iter: impl IntoIterator<Item = T>) -> RBTree<T> {
+        let mut tree = Self::new();
+        let items = tree.group_sorted(iter);
+        tree.build(items);
+        tree
+    }
+}
+
+impl<T: Debug> RBTree<T> {
+    // keeps only the values for which f is true, in O(n)
+    // (f is called once per distinct value and decides for all its copies)
+    pub fn retain(&mut self, mut f: impl FnMut(&T) -> bool) {
+        let items = self.take_sorted();
+        self.build(items.into_iter().filter(|(data, _)| f(data)).collect());
+    }
+
+    // takes the values within the bounds out of the tree, in O(k log n)
+    pub fn drain_range(&mut self, range: impl RangeBounds<T>) -> IntoIter<T> {
+        let range = self.range(range);
+        let (mut c, remaining) = (range.front, range.remaining);
+        let mut order = vec![];
+        let mut counted = 0;
+        while counted < remaining {
+            let Some(current) = c else { break };
+            counted += self.node(current).cnt;
+            order.push(current);
+            c = self.next_node(current);
+        }
+
+        let nodes = order
+            .into_iter()
+            .filter_map(|node| {
+                let cnt = self.node(node).cnt;
+                self.delete_node(node).map(|data| (data, cnt))
+            })
+            .collect();
+        IntoIter { nodes, remaining }
+    }
+
+    // moves values greater than or equal to key into a new tree
+    // (both trees are rebuilt from their sorted values in O(n), not split
+    // along the search path in O(log n))
+    pub fn split_off(&mut self, key: &T) -> RBTree<T> {
+        let mut items = self.take_sorted();
+        let at = items.partition_point(|(data, _)| self.lt(data, key));
+        let upper = items.split_off(at);
+
+        let mut other = RBTree::with_cmp(self.cmp.clone());
+        other.augment = self.augment;
+        self.build(items);
+        other.build(upper);
+        other
+    }
+
+    // moves all values of other into this tree
+    // (the sorted values of both are merged and rebuilt in O(n + m), not
+    // joined by black height in O(log n); other is expected to be ordered
+    // the same way as this tree)
+    pub fn append(&mut self, other: &mut RBTree<T>) {
+        if other.root.is_none() {
+            return;
+        }
+        let mut lower = self.take_sorted().into_iter().peekable();
+        let mut upper = other.take_sorted().into_iter().peekable();
+
+        let mut items = Vec::with_capacity(lower.len() + upper.len());
+        while let (Some((a, _)), Some((b, _))) = (lower.peek(), upper.peek()) {
+            match self.compare(a, b) {
+                Some(Ordering::Less) => items.extend(lower.next()),
+                Some(Ordering::Greater) => items.extend(upper.next()),
+                Some(Ordering::Equal) => {
+                    // one node with both counts keeps the run strictly ascending
+                    let (a, a_cnt) = lower.next().unwrap();
+                    let (_, b_cnt) = upper.next().unwrap();
+                    items.push((a, a_cnt + b_cnt));
+                }
+                None => {
+                    // not comparable values are left for `build` to sort out
+                    items.extend(lower.next());
+                    items.extend(upper.next());
+                }
+            }
+        }
+        items.extend(lower);
+        items.extend(upper);
+
+        self.build(items);
+    }
+
+    // comparable values with the count of equal neighbors
+    fn group_sorted(&self, iter: impl IntoIterator<Item = T>) -> Vec<(T, usize)> {
+        let mut items: Vec<(T, usize)> = vec![];
+        for data in iter.into_iter().filter(|data| self.comparable(data)) {
+            match items.last_mut() {
+                Some((last, cnt)) if self.compare(last, &data) == Some(Ordering::Equal) => {
+                    *cnt += 1
+                }
+                _ => items.push((data, 1)),
+            }
+        }
+        items
+    }
+
+    // checks counted values read from outside before they are built into
+    // the tree, so broken input can't overflow counts or poison the order
+    pub(super) fn check_items(&self, items: &[(T, usize)]) -> Result<(), &'static str> {
+        let mut total = 0usize;
+        for (data, cnt) in items {
+            if *cnt == 0 {
+                return Err("count of a value must be at least 1");
+            }
+            total = total
+                .checked_add(*cnt)
+                .ok_or("counts add up to more than usize::MAX")?;
+            if !self.comparable(data) {
+                return Err("value is not comparable");
+            }
+        }
+        Ok(())
+    }
+
+    // takes every node out in ascending order and leaves the tree empty
+    fn take_sorted(&mut self) -> Vec<(T, usize)> {
+        let mut order = Vec::with_capacity(self.nodes.len() - self.free.len());
+        let mut c = self.root.map(|root| self.min_node(root));
+        while let Some(current) = c {
+            order.push(current);
+            c = self.next_node(current);
+        }
+
+        let items = order
+            .into_iter()
+            .filter_map(|slot| self.nodes[slot].take())
+            .map(|node| (node.data, node.cnt))
+            .collect();
+
+        self.nodes.clear();
+        self.free.clear();
+        self.root = None;
+        self.cnt = 0;
+        items
+    }
+
+    // fills the empty tree with items: the strictly ascending leading run
+    // is linked as a balanced tree, and the rest is inserted one by one
+    pub(super) fn build(&mut self, items: Vec<(T, usize)>) {
+        let mut sorted_len = items.len().min(1);
+        while sorted_len < items.len() && self.lt(&items[sorted_len - 1].0, &items[sorted_len].0) {
+            sorted_len += 1;
+        }
+
+        let mut items = items.into_iter();
+        let height = usize::BITS - sorted_len.leading_zeros();
+        self.nodes.reserve(items.len());
+        self.root = self.build_subtree(sorted_len, 1, height, &mut items);
+        self.cnt = self.get_size(self.root);
+
+        for (data, cnt) in items {
+            self.insert_with_cnt(data, cnt);
+        }
+    }
+
+    // links len items as a subtree of which every leaf is at the bottom two
+    // levels; only nodes on the very bottom level of an incomplete tree are red
+    fn build_subtree(
+        &mut self,
+        len: usize,
+        depth: u32,
+        height: u32,
+        items: &mut impl Iterator<Item = (T, usize)>,
+    ) -> Option<usize> {
+        if len == 0 {
+            return None;
+        }
+        let left_len = (len - 1) / 2;
+        let lchild = self.build_subtree(left_len, depth + 1, height, items);
+
+        let (data, cnt) = items.next()?;
+        let mut new_node = RBTreeNode::new(data);
+        new_node.cnt = cnt;
+        new_node.color = if depth == height && depth > 1 {
+            Color::Red
+        } else {
+            Color::Black
+        };
+        new_node.lchild = lchild;
+        let node = self.alloc(new_node);
+        if let Some(lc) = lchild {
+            self.node_mut(lc).parent = Some(node);
+        }
+
+        let rchild = self.build_subtree(len - 1 - left_len, depth + 1, height, items);
+        self.node_mut(node).rchild = rchild;
+        if let Some(rc) = rchild {
+            self.node_mut(rc).parent = Some(node);
+        }
+
+        self.update_size(node);
+        Some(node)
+    }
+
+    fn insert_with_cnt(&mut self, data: T, cnt: usize) {
+        match self.search(&data) {
+            Some(Place::Found(node)) => {
+                self.node_mut(node).cnt += cnt;
+                self.cnt += cnt;
+                self.update_size_upward(Some(node));
+            }
+            Some(Place::Vacant(parent, side)) => {
+                self.link(parent, side, data, cnt);
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{model_test::test_rng, RBTree};
+    use rand::prelude::*;
+    #[test]
+    fn from_sorted_iter_test() {
+        for n in 0..100 {
+            let rbt = RBTree::<u64>::from_sorted_iter(0..n);
+            assert!(rbt.validate().is_ok());
+            assert!(rbt.check().0);
+            assert!(rbt.iter().eq((0..n).collect::<Vec<u64>>().iter()));
+        }
+
+        let mut rng = test_rng();
+        let mut nums: Vec<u64> = (0..10000).map(|_| rng.gen_range(0..5000)).collect();
+        nums.sort();
+        let mut rbt = RBTree::<u64>::from_sorted_iter(nums.clone());
+        assert!(rbt.validate().is_ok());
+        assert!(rbt.iter().eq(nums.iter()));
+        for n in &nums {
+            assert!(rbt.delete(n));
+            assert!(rbt.check().0);
+        }
+
+        // out of order values are inserted one by one
+        let nums = vec![1.0, 2.0, 3.0, 0.5, 2.0, f64::NAN, 4.0];
+        let rbt = RBTree::<f64>::from_sorted_iter(nums);
+        assert!(rbt.validate().is_ok());
+        assert!(rbt.iter().eq([0.5, 1.0, 2.0, 2.0, 3.0, 4.0].iter()));
+
+        // even at the front, which nothing else could be compared with
+        let rbt = RBTree::<f64>::from_sorted_iter([f64::NAN, 1.0, 2.0]);
+        assert!(rbt.validate().is_ok());
+        assert!(rbt.iter().eq([1.0, 2.0].iter()));
+    }
+    #[test]
+    fn retain_drain_range_test() {
+        let mut rng = test_rng();
+        let mut nums: Vec<u64> = (0..5000).map(|_| rng.gen_range(0..1000)).collect();
+        let mut rbt = RBTree::<u64>::new();
+        for n in &nums {
+            rbt.insert(*n);
+        }
+        nums.sort();
+
+        rbt.retain(|n| n % 3 != 0);
+        nums.retain(|n| n % 3 != 0);
+        assert!(rbt.validate().is_ok());
+        assert!(rbt.iter().eq(nums.iter()));
+
+        let drained: Vec<u64> = rbt.drain_range(200..400).collect();
+        let expected: Vec<u64> = nums
+            .iter()
+            .copied()
+            .filter(|n| (200..400).contains(n))
+            .collect();
+        nums.retain(|n| !(200..400).contains(n));
+        assert_eq!(drained, expected);
+        assert!(rbt.validate().is_ok());
+        assert!(rbt.iter().eq(nums.iter()));
+
+        assert_eq!(rbt.drain_range(200..400).len(), 0);
+        let drained = rbt.drain_range(..);
+        assert_eq!(drained.len(), nums.len());
+        assert!(drained.eq(nums.into_iter()));
+        assert_eq!(rbt.len(), 0);
+        assert!(rbt.validate().is_ok());
+    }
+    #[test]
+    fn split_off_append_test() {
+        let mut rng = test_rng();
+        let mut nums: Vec<u64> = (0..10000).map(|_| rng.gen_range(0..1000)).collect();
+        let mut rbt = RBTree::<u64>::new();
+        for n in &nums {
+            rbt.insert(*n);
+        }
+        nums.sort();
+
+        let mut upper = rbt.split_off(&500);
+        assert!(rbt.validate().is_ok());
+        assert!(upper.validate().is_ok());
+        assert!(rbt.iter().eq(nums.iter().filter(|&&n| n < 500)));
+        assert!(upper.iter().eq(nums.iter().filter(|&&n| n >= 500)));
+
+        // overlapping values are merged into duplicates
+        let mut other = RBTree::<u64>::from_sorted_iter(400..600);
+        upper.append(&mut other);
+        assert_eq!(other.len(), 0);
+        assert!(other.validate().is_ok());
+        rbt.append(&mut upper);
+        assert!(rbt.validate().is_ok());
+        nums.extend(400..600);
+        nums.sort();
+        assert!(rbt.iter().eq(nums.iter()));
+
+        // overlapping trees are still linked as one balanced tree
+        let mut lower = RBTree::<u64>::from_sorted_iter(0..1000);
+        let mut upper = RBTree::<u64>::from_sorted_iter(500..1500);
+        lower.append(&mut upper);
+        assert!(lower.validate().is_ok());
+        assert_eq!(lower.len(), 2000);
+        assert_eq!(lower.count_range(700..=700), 2);
+        assert_eq!(lower.check().3, usize::BITS - 1500usize.leading_zeros());
+
+        let mut reversed = RBTree::<u64>::with_comparator(|a, b| b.cmp(a));
+        for n in 0..100 {
+            reversed.insert(n);
+        }
+        let lower = reversed.split_off(&49);
+        assert!(reversed
+            .iter()
+            .eq((50..100).rev().collect::<Vec<u64>>().iter()));
+        assert!(lower.iter().eq((0..50).rev().collect::<Vec<u64>>().iter()));
+        assert!(lower.validate().is_ok());
+    }
+}
@@ -0,0 +1,301 @@
+use super::{Child, RBTree};
+use std::{
+    cmp::Ordering,
+    error::Error,
+    fmt::{self, Debug},
+};
+
+// position in a tree: on a node, or on the "ghost" between the last and the
+// first value (as `std::collections::linked_list::Cursor`); moving is O(1)
+// amortized, as it walks parent links instead of searching from the root
+pub struct Cursor<'a, T: Debug> {
+    tree: &'a RBTree<T>,
+    node: Option<usize>,
+}
+
+// `Cursor` which can also remove the current value and insert next to it
+pub struct CursorMut<'a, T: Debug> {
+    tree: &'a mut RBTree<T>,
+    node: Option<usize>,
+}
+
+// a value inserted by a cursor would not be in order there (or is not
+// comparable with its neighbors)
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrderError<T> {
+    pub data: T,
+}
+
+impl<T: Debug> fmt::Display for OrderError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} is out of order at the cursor", self.data)
+    }
+}
+
+impl<T: Debug> Error for OrderError<T> {}
+
+impl<T: Debug> RBTree<T> {
+    pub fn cursor_front(&self) -> Cursor<'_, T> {
+        let node = self.step_next(None);
+        Cursor { tree: self, node }
+    }
+
+    pub fn cursor_back(&self) -> Cursor<'_, T> {
+        let node = self.step_prev(None);
+        Cursor { tree: self, node }
+    }
+
+    // on the first value not less than data (the ghost if there is none)
+    pub fn cursor_lower_bound(&self, data: &T) -> Cursor<'_, T> {
+        let node = self.first_node_not(|d| self.lt(d, data));
+        Cursor { tree: self, node }
+    }
+
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        let node = self.step_next(None);
+        CursorMut { tree: self, node }
+    }
+
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T> {
+        let node = self.step_prev(None);
+        CursorMut { tree: self, node }
+    }
+
+    pub fn cursor_lower_bound_mut(&mut self, data: &T) -> CursorMut<'_, T> {
+        let node = self.first_node_not(|d| self.lt(d, data));
+        CursorMut { tree: self, node }
+    }
+
+    // next node, where the ghost (None) is before the first one
+    fn step_next(&self, node: Option<usize>) -> Option<usize> {
+        match node {
+            Some(node) => self.next_node(node),
+            None => self.root.map(|root| self.min_node(root)),
+        }
+    }
+
+    // previous node, where the ghost (None) is after the last one
+    fn step_prev(&self, node: Option<usize>) -> Option<usize> {
+        match node {
+            Some(node) => self.prev_node(node),
+            None => self.root.map(|root| self.max_node(root)),
+        }
+    }
+
+    fn data_at(&self, node: Option<usize>) -> Option<&T> {
+        node.map(|node| &self.node(node).data)
+    }
+
+    fn add_copy(&mut self, node: usize) {
+        self.node_mut(node).cnt += 1;
+        self.cnt += 1;
+        self.update_size_upward(Some(node));
+    }
+
+    // links data between two neighbor nodes (None for either end), or adds
+    // a copy to one of them if it is equal
+    fn insert_between(
+        &mut self,
+        prev: Option<usize>,
+        next: Option<usize>,
+        data: T,
+    ) -> Result<(), OrderError<T>> {
+        // checked even with no neighbor to compare with, as by `insert`
+        if !self.comparable(&data) {
+            return Err(OrderError { data });
+        }
+        if let Some(prev) = prev {
+            match self.compare(&data, &self.node(prev).data) {
+                Some(Ordering::Greater) => {}
+                Some(Ordering::Equal) => {
+                    self.add_copy(prev);
+                    return Ok(());
+                }
+                _ => return Err(OrderError { data }),
+            }
+        }
+        if let Some(next) = next {
+            match self.compare(&data, &self.node(next).data) {
+                Some(Ordering::Less) => {}
+                Some(Ordering::Equal) => {
+                    self.add_copy(next);
+                    return Ok(());
+                }
+                _ => return Err(OrderError { data }),
+            }
+        }
+        // either prev has no right child, or next (the min of that right
+        // subtree, or of the tree) has no left child
+        match (prev, next) {
+            (Some(prev), _) if self.node(prev).rchild.is_none() => {
+                self.link(Some(prev), Child::Right, data, 1)
+            }
+            (_, Some(next)) => self.link(Some(next), Child::Left, data, 1),
+            _ => self.link(None, Child::None, data, 1),
+        };
+        Ok(())
+    }
+}
+
+impl<'a, T: Debug> Cursor<'a, T> {
+    // None on the ghost
+    pub fn current(&self) -> Option<&'a T> {
+        self.tree.data_at(self.node)
+    }
+
+    // copies of the current value (0 on the ghost)
+    pub fn count(&self) -> usize {
+        self.node.map_or(0, |node| self.tree.node(node).cnt)
+    }
+
+    pub fn move_next(&mut self) {
+        self.node = self.tree.step_next(self.node);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.node = self.tree.step_prev(self.node);
+    }
+
+    pub fn peek_next(&self) -> Option<&'a T> {
+        self.tree.data_at(self.tree.step_next(self.node))
+    }
+
+    pub fn peek_prev(&self) -> Option<&'a T> {
+        self.tree.data_at(self.tree.step_prev(self.node))
+    }
+}
+
+impl<T: Debug> CursorMut<'_, T> {
+    pub fn current(&self) -> Option<&T> {
+        self.tree.data_at(self.node)
+    }
+
+    pub fn count(&self) -> usize {
+        self.as_cursor().count()
+    }
+
+    pub fn move_next(&mut self) {
+        self.node = self.tree.step_next(self.node);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.node = self.tree.step_prev(self.node);
+    }
+
+    pub fn peek_next(&self) -> Option<&T> {
+        self.tree.data_at(self.tree.step_next(self.node))
+    }
+
+    pub fn peek_prev(&self) -> Option<&T> {
+        self.tree.data_at(self.tree.step_prev(self.node))
+    }
+
+    // read-only cursor at the same position
+    pub fn as_cursor(&self) -> Cursor<'_, T> {
+        Cursor {
+            tree: self.tree,
+            node: self.node,
+        }
+    }
+
+    // removes the current value with all its copies and moves to the next
+    // one; None on the ghost
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.node?;
+        self.node = self.tree.next_node(node);
+        self.tree.delete_node(node)
+    }
+
+    // inserts data right after the current value (at the front on the
+    // ghost) and stays where it is
+    pub fn insert_after(&mut self, data: T) -> Result<(), OrderError<T>> {
+        let next = self.tree.step_next(self.node);
+        self.tree.insert_between(self.node, next, data)
+    }
+
+    // inserts data right before the current value (at the back on the
+    // ghost) and stays where it is
+    pub fn insert_before(&mut self, data: T) -> Result<(), OrderError<T>> {
+        let prev = self.tree.step_prev(self.node);
+        self.tree.insert_between(prev, self.node, data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::{cursor::OrderError, RBTree};
+    #[test]
+    fn cursor_test() {
+        let rbt = RBTree::<u64>::from_sorted_iter([1, 3, 3, 5, 7]);
+        let mut cursor = rbt.cursor_front();
+        let mut values = vec![];
+        while let Some(n) = cursor.current() {
+            values.push((*n, cursor.count()));
+            cursor.move_next();
+        }
+        assert_eq!(values, [(1, 1), (3, 2), (5, 1), (7, 1)]);
+        // past the end is the ghost, and then the front again
+        assert_eq!(cursor.count(), 0);
+        assert_eq!(cursor.peek_next(), Some(&1));
+        assert_eq!(cursor.peek_prev(), Some(&7));
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&7));
+
+        let cursor = rbt.cursor_lower_bound(&4);
+        assert_eq!(cursor.current(), Some(&5));
+        assert_eq!(
+            (cursor.peek_prev(), cursor.peek_next()),
+            (Some(&3), Some(&7))
+        );
+        assert_eq!(rbt.cursor_lower_bound(&8).current(), None);
+        assert_eq!(rbt.cursor_back().current(), Some(&7));
+        assert_eq!(RBTree::<u64>::new().cursor_front().current(), None);
+    }
+    #[test]
+    fn cursor_mut_test() {
+        let mut rbt = RBTree::<u64>::from_sorted_iter((0..100).map(|n| n * 10));
+        let mut cursor = rbt.cursor_front_mut();
+        // drops every multiple of 20 and puts n + 5 after each other value
+        while let Some(&n) = cursor.current() {
+            if n % 20 == 0 {
+                assert_eq!(cursor.remove_current(), Some(n));
+            } else {
+                assert_eq!(cursor.insert_after(n + 5), Ok(()));
+                cursor.move_next();
+                assert_eq!(cursor.current(), Some(&(n + 5)));
+                cursor.move_next();
+            }
+        }
+        assert!(rbt.validate().is_ok());
+        let expected: Vec<u64> = (0..50).flat_map(|n| [n * 20 + 10, n * 20 + 15]).collect();
+        assert!(rbt.iter().eq(expected.iter()));
+
+        let mut cursor = rbt.cursor_lower_bound_mut(&30);
+        assert_eq!(cursor.insert_before(20), Ok(()));
+        assert_eq!(cursor.insert_before(12), Err(OrderError { data: 12 }));
+        assert_eq!(cursor.insert_after(30), Ok(()));
+        assert_eq!(cursor.insert_after(35), Ok(()));
+        assert_eq!(cursor.count(), 2);
+        assert_eq!(cursor.peek_prev(), Some(&20));
+        assert_eq!(cursor.as_cursor().peek_next(), Some(&35));
+
+        // the ghost inserts at either end
+        let mut cursor = rbt.cursor_back_mut();
+        cursor.move_next();
+        assert_eq!(cursor.insert_after(0), Ok(()));
+        assert_eq!(cursor.insert_before(1000), Ok(()));
+        assert_eq!(cursor.insert_before(5), Err(OrderError { data: 5 }));
+        assert_eq!(rbt.min(), Some(&0));
+        assert_eq!(rbt.max(), Some(&1000));
+        assert_eq!(rbt.len(), 105);
+        assert!(rbt.validate().is_ok());
+
+        let mut empty = RBTree::<f64>::new();
+        let mut cursor = empty.cursor_front_mut();
+        assert_eq!(cursor.remove_current(), None);
+        assert!(cursor.insert_after(f64::NAN).is_err());
+        assert_eq!(cursor.insert_after(1.0), Ok(()));
+        assert!(cursor.insert_after(f64::NAN).is_err());
+        assert_eq!(empty.len(), 1);
+    }
+}
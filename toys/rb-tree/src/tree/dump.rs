@@ -0,0 +1,179 @@
+use super::RBTree;
+use std::{
+    fmt::Debug,
+    io::{self, Read, Write},
+};
+
+// binary dump of a tree:
+//   b"RBT\x01", number of distinct values, then each value followed by its count
+// in ascending order; numbers of the format are LEB128 varints.
+const MAGIC: &[u8; 4] = b"RBT\x01";
+
+// value which can be written into a dump
+pub trait DumpValue: Sized {
+    fn dump_to(&self, w: &mut dyn Write) -> io::Result<()>;
+    fn restore_from(r: &mut dyn Read) -> io::Result<Self>;
+}
+
+macro_rules! dump_value_le_bytes {
+    ($($t:ty),*) => {$(
+        impl DumpValue for $t {
+            fn dump_to(&self, w: &mut dyn Write) -> io::Result<()> {
+                w.write_all(&self.to_le_bytes())
+            }
+
+            fn restore_from(r: &mut dyn Read) -> io::Result<Self> {
+                let mut buf = [0; std::mem::size_of::<$t>()];
+                r.read_exact(&mut buf)?;
+                Ok(<$t>::from_le_bytes(buf))
+            }
+        }
+    )*};
+}
+
+dump_value_le_bytes!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl DumpValue for String {
+    fn dump_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        write_varint(w, self.len() as u64)?;
+        w.write_all(self.as_bytes())
+    }
+
+    fn restore_from(r: &mut dyn Read) -> io::Result<Self> {
+        let len = read_varint(r)?;
+        let mut buf = vec![];
+        r.take(len).read_to_end(&mut buf)?;
+        if buf.len() as u64 != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+fn write_varint(w: &mut dyn Write, mut n: u64) -> io::Result<()> {
+    while n >= 0x80 {
+        w.write_all(&[n as u8 | 0x80])?;
+        n >>= 7;
+    }
+    w.write_all(&[n as u8])
+}
+
+fn read_varint(r: &mut dyn Read) -> io::Result<u64> {
+    let mut n = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        r.read_exact(&mut byte)?;
+        // only the lowest bit of the 10th byte is left to fill
+        if shift == 63 && byte[0] > 1 {
+            break;
+        }
+        n |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] < 0x80 {
+            return Ok(n);
+        }
+    }
+    Err(invalid_data("varint is longer than 64 bits"))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl<T: DumpValue + Debug> RBTree<T> {
+    pub fn dump(&self, mut w: impl Write) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        write_varint(&mut w, self.node_iter().count() as u64)?;
+        for (data, cnt) in self.node_iter() {
+            data.dump_to(&mut w)?;
+            write_varint(&mut w, cnt as u64)?;
+        }
+        w.flush()
+    }
+}
+
+impl<T: DumpValue + PartialOrd + Debug> RBTree<T> {
+    // reloads a dump in O(n) (values out of order are inserted one by one)
+    pub fn restore(mut r: impl Read) -> io::Result<RBTree<T>> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a dump of RBTree"));
+        }
+
+        let len = read_varint(&mut r)?;
+        // a broken length must not allocate everything at once
+        let mut items = Vec::with_capacity(len.min(1 << 16) as usize);
+        for _ in 0..len {
+            let data = T::restore_from(&mut r)?;
+            let cnt = read_varint(&mut r)?;
+            let cnt = usize::try_from(cnt).map_err(|_| invalid_data("count is too large"))?;
+            items.push((data, cnt));
+        }
+
+        let mut tree = RBTree::new();
+        tree.check_items(&items).map_err(invalid_data)?;
+        tree.build(items);
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{model_test::test_rng, tree::RBTree};
+    use rand::prelude::*;
+    use std::io::ErrorKind;
+    #[test]
+    fn dump_restore_test() {
+        let mut rng = test_rng();
+        let mut rbt = RBTree::<u64>::new();
+        for _ in 0..10000 {
+            rbt.insert(rng.gen_range(0..1000) * 1000);
+        }
+        let mut buf = vec![];
+        rbt.dump(&mut buf).unwrap();
+        let distinct = rbt.iter().collect::<std::collections::BTreeSet<_>>().len();
+        assert!(buf.len() <= 4 + 2 + distinct * (8 + 1));
+
+        let restored = RBTree::<u64>::restore(buf.as_slice()).unwrap();
+        assert!(restored.validate().is_ok());
+        assert!(restored.iter().eq(rbt.iter()));
+
+        let words =
+            RBTree::<String>::from_sorted_iter(["ant", "bee", "bee", "가나다"].map(String::from));
+        let mut buf = vec![];
+        words.dump(&mut buf).unwrap();
+        let restored = RBTree::<String>::restore(&buf[..]).unwrap();
+        assert!(restored.iter().eq(words.iter()));
+
+        // broken dumps
+        let err = RBTree::<String>::restore(&buf[..buf.len() - 2]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        let err = RBTree::<u64>::restore(&b"RBT\x02\x00"[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = RBTree::<u8>::restore(&b"RBT\x01\x01\x07\x00"[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // counts which overflow together, and NaN
+        let mut buf = b"RBT\x01\x02".to_vec();
+        for n in [1u8, 2] {
+            buf.push(n);
+            buf.extend([0xff; 9]);
+            buf.push(0x01);
+        }
+        let err = RBTree::<u8>::restore(&buf[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        // a count with bits beyond 64
+        let mut buf = b"RBT\x01\x01\x01".to_vec();
+        buf.extend([0xff; 9]);
+        buf.push(0x02);
+        let err = RBTree::<u8>::restore(&buf[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let mut buf = vec![];
+        RBTree::<f64>::from_sorted_iter([1.0])
+            .dump(&mut buf)
+            .unwrap();
+        buf[5..13].copy_from_slice(&f64::NAN.to_le_bytes());
+        let err = RBTree::<f64>::restore(&buf[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
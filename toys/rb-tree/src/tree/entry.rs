@@ -0,0 +1,137 @@
+use super::{IncomparableError, Place, RBTree};
+use std::fmt::Debug;
+
+// place of a value in the tree, found by a single search, to look at and
+// change how many copies of the value are stored
+pub struct CountEntry<'a, T: Debug> {
+    tree: &'a mut RBTree<T>,
+    data: T,
+    place: Place,
+}
+
+impl<T: Debug> RBTree<T> {
+    pub fn entry(&mut self, data: T) -> Result<CountEntry<'_, T>, IncomparableError<T>> {
+        match self.search(&data) {
+            Some(place) => Ok(CountEntry {
+                tree: self,
+                data,
+                place,
+            }),
+            None => Err(IncomparableError { data }),
+        }
+    }
+}
+
+impl<T: Debug> CountEntry<'_, T> {
+    // the value stored in the tree, or the one given to `entry` if there is none
+    pub fn get(&self) -> &T {
+        match self.place {
+            Place::Found(node) => &self.tree.node(node).data,
+            Place::Vacant(..) => &self.data,
+        }
+    }
+
+    // copies of the value in the tree
+    pub fn count(&self) -> usize {
+        match self.place {
+            Place::Found(node) => self.tree.node(node).cnt,
+            Place::Vacant(..) => 0,
+        }
+    }
+
+    // inserts n copies and returns the new count
+    // (panics if the tree would hold more than usize::MAX values, as no
+    // count in it could be right any more)
+    pub fn insert(self, n: usize) -> usize {
+        let total = self
+            .tree
+            .cnt
+            .checked_add(n)
+            .expect("values in the tree overflow usize");
+        match self.place {
+            Place::Found(node) => {
+                // no more than the total, which didn't overflow
+                self.tree.node_mut(node).cnt += n;
+                self.tree.cnt = total;
+                self.tree.update_size_upward(Some(node));
+                self.tree.node(node).cnt
+            }
+            Place::Vacant(_, _) if n == 0 => 0,
+            Place::Vacant(parent, side) => {
+                self.tree.link(parent, side, self.data, n);
+                n
+            }
+        }
+    }
+
+    // removes up to n copies and returns how many were removed
+    pub fn remove(self, n: usize) -> usize {
+        let node = match self.place {
+            Place::Found(node) => node,
+            Place::Vacant(..) => return 0,
+        };
+        let cnt = self.tree.node(node).cnt;
+        if n >= cnt {
+            self.tree.delete_node(node);
+            return cnt;
+        }
+        self.tree.node_mut(node).cnt -= n;
+        self.tree.cnt -= n;
+        self.tree.update_size_upward(Some(node));
+        n
+    }
+
+    // removes every copy and returns how many there were
+    pub fn remove_all(self) -> usize {
+        self.remove(usize::MAX)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::RBTree;
+    #[test]
+    fn entry_test() {
+        let mut rbt = RBTree::<u64>::new();
+        assert_eq!(rbt.entry(5).unwrap().count(), 0);
+        assert_eq!(rbt.entry(5).unwrap().insert(0), 0);
+        assert_eq!(rbt.len(), 0);
+
+        let mut counts = [0; 10];
+        for n in 0..100 {
+            counts[n % 10] += n % 3 + 1;
+            assert_eq!(
+                rbt.entry(n as u64 % 10).unwrap().insert(n % 3 + 1),
+                counts[n % 10]
+            );
+            assert!(rbt.validate().is_ok());
+        }
+        assert_eq!(rbt.len(), counts.iter().sum::<usize>());
+        assert_eq!(rbt.count_range(3..=3), counts[3]);
+
+        assert_eq!(rbt.entry(3).unwrap().remove(1), 1);
+        assert_eq!(rbt.entry(3).unwrap().count(), counts[3] - 1);
+        assert_eq!(rbt.entry(3).unwrap().remove_all(), counts[3] - 1);
+        assert!(!rbt.find(&3));
+        assert_eq!(rbt.entry(3).unwrap().remove(1), 0);
+        assert_eq!(rbt.entry(4).unwrap().remove(1000), counts[4]);
+        assert!(!rbt.find(&4));
+        assert_eq!(
+            rbt.len(),
+            counts.iter().sum::<usize>() - counts[3] - counts[4]
+        );
+        assert!(rbt.validate().is_ok());
+
+        let mut floats = RBTree::<f64>::new();
+        assert_eq!(floats.entry(1.5).unwrap().insert(2), 2);
+        assert_eq!(*floats.entry(1.5).unwrap().get(), 1.5);
+        assert!(floats.entry(f64::NAN).is_err());
+    }
+    #[test]
+    #[should_panic(expected = "overflow usize")]
+    fn entry_overflow_test() {
+        let mut rbt = RBTree::<u64>::new();
+        rbt.entry(1).unwrap().insert(usize::MAX);
+        rbt.entry(2).unwrap().insert(1);
+    }
+}
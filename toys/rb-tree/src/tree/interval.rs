@@ -0,0 +1,256 @@
+use super::RBTree;
+use std::{
+    cmp::Ordering,
+    fmt::{self, Debug},
+};
+
+// closed interval [lo, hi], ordered by lo and then by hi
+pub struct Interval<K> {
+    pub lo: K,
+    pub hi: K,
+    max: K, // max `hi` in the subtree of the node holding this interval
+}
+
+impl<K: PartialOrd> PartialEq for Interval<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.lo == other.lo && self.hi == other.hi
+    }
+}
+
+impl<K: PartialOrd> PartialOrd for Interval<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.lo.partial_cmp(&other.lo)? {
+            Ordering::Equal => self.hi.partial_cmp(&other.hi),
+            ord => Some(ord),
+        }
+    }
+}
+
+impl<K: Debug> Debug for Interval<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{:?}, {:?}]", self.lo, self.hi)
+    }
+}
+
+impl<K: PartialOrd> Interval<K> {
+    // shares at least one point with [lo, hi]
+    pub fn overlaps(&self, lo: &K, hi: &K) -> bool {
+        self.lo <= *hi && *lo <= self.hi
+    }
+}
+
+fn max_endpoint<'a, K: PartialOrd>(
+    interval: &'a Interval<K>,
+    l: Option<&'a Interval<K>>,
+    r: Option<&'a Interval<K>>,
+) -> &'a K {
+    let mut max = &interval.hi;
+    for child in [l, r].into_iter().flatten() {
+        if child.max > *max {
+            max = &child.max;
+        }
+    }
+    max
+}
+
+fn update_max<K: PartialOrd + Clone>(
+    interval: &mut Interval<K>,
+    l: Option<&Interval<K>>,
+    r: Option<&Interval<K>>,
+) {
+    interval.max = max_endpoint(interval, l, r).clone();
+}
+
+// red-black tree of intervals; every node keeps the max endpoint of its
+// subtree, so subtrees ending before a query are skipped
+pub struct IntervalTree<K: PartialOrd + Clone + Debug> {
+    tree: RBTree<Interval<K>>,
+}
+
+impl<K: PartialOrd + Clone + Debug> Debug for IntervalTree<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K: PartialOrd + Clone + Debug> Default for IntervalTree<K> {
+    fn default() -> Self {
+        IntervalTree::new()
+    }
+}
+
+impl<K: PartialOrd + Clone + Debug> IntervalTree<K> {
+    pub fn new() -> IntervalTree<K> {
+        let mut tree = RBTree::new();
+        tree.augment = Some(update_max);
+        IntervalTree { tree }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // returns false if lo > hi (or they are not comparable)
+    pub fn insert(&mut self, lo: K, hi: K) -> bool {
+        if let None | Some(Ordering::Greater) = lo.partial_cmp(&hi) {
+            return false;
+        }
+        let max = hi.clone();
+        self.tree.insert(Interval { lo, hi, max })
+    }
+
+    // removes one copy of [lo, hi]
+    pub fn delete(&mut self, lo: &K, hi: &K) -> bool {
+        self.tree.delete(&Interval {
+            lo: lo.clone(),
+            hi: hi.clone(),
+            max: hi.clone(),
+        })
+    }
+
+    // iterates in ascending order of lo (and then hi)
+    pub fn iter(&self) -> super::Iter<'_, Interval<K>> {
+        self.tree.iter()
+    }
+
+    // intervals containing x, in ascending order
+    pub fn overlapping_point(&self, x: &K) -> Vec<&Interval<K>> {
+        self.overlapping(x, x)
+    }
+
+    // intervals sharing at least one point with [lo, hi], in ascending order
+    pub fn overlapping(&self, lo: &K, hi: &K) -> Vec<&Interval<K>> {
+        let mut found = vec![];
+        self.collect_overlapping(self.tree.root, lo, hi, &mut found);
+        found
+    }
+
+    fn collect_overlapping<'a>(
+        &'a self,
+        node: Option<usize>,
+        lo: &K,
+        hi: &K,
+        found: &mut Vec<&'a Interval<K>>,
+    ) {
+        let current = match node {
+            Some(node) => self.tree.node(node),
+            None => return,
+        };
+        // every interval of the subtree ends before lo
+        if current.data.max < *lo {
+            return;
+        }
+        self.collect_overlapping(current.lchild, lo, hi, found);
+        // this interval and every one on the right start after hi
+        if current.data.lo > *hi {
+            return;
+        }
+        if current.data.overlaps(lo, hi) {
+            found.extend(std::iter::repeat_n(&current.data, current.cnt));
+        }
+        self.collect_overlapping(current.rchild, lo, hi, found);
+    }
+
+    // `RBTree::check` which also fails if a max endpoint is out of date
+    pub fn check(&self) -> (bool, u32, u32, u32) {
+        let tree = &self.tree;
+        let max_ok = tree.nodes.iter().flatten().all(|node| {
+            let l = node.lchild.map(|lc| &tree.node(lc).data);
+            let r = node.rchild.map(|rc| &tree.node(rc).data);
+            *max_endpoint(&node.data, l, r) == node.data.max
+        });
+        match tree.check() {
+            (true, black, min_depth, max_depth) if max_ok => (true, black, min_depth, max_depth),
+            _ => (false, 0, 0, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{model_test::test_rng, tree::interval::IntervalTree};
+    use rand::prelude::*;
+    #[test]
+    fn overlap_test() {
+        let mut rng = test_rng();
+        let mut tree = IntervalTree::<u32>::new();
+        let mut intervals: Vec<(u32, u32)> = vec![];
+        for i in 0..3000 {
+            if i % 3 == 2 && !intervals.is_empty() {
+                let (lo, hi) = intervals.swap_remove(rng.gen_range(0..intervals.len()));
+                assert!(tree.delete(&lo, &hi));
+            } else {
+                let lo = rng.gen_range(0..10000);
+                let hi = lo + rng.gen_range(0..500);
+                assert!(tree.insert(lo, hi));
+                intervals.push((lo, hi));
+            }
+            assert!(tree.check().0);
+        }
+        assert_eq!(tree.len(), intervals.len());
+        intervals.sort();
+
+        for _ in 0..300 {
+            let a = rng.gen_range(0..10500);
+            let b = a + rng.gen_range(0..100);
+            let expected: Vec<(u32, u32)> = intervals
+                .iter()
+                .copied()
+                .filter(|&(lo, hi)| lo <= b && a <= hi)
+                .collect();
+            let found: Vec<(u32, u32)> = tree
+                .overlapping(&a, &b)
+                .iter()
+                .map(|interval| (interval.lo, interval.hi))
+                .collect();
+            assert_eq!(found, expected);
+
+            let at_a: Vec<(u32, u32)> = tree
+                .overlapping_point(&a)
+                .iter()
+                .map(|interval| (interval.lo, interval.hi))
+                .collect();
+            assert!(tree
+                .overlapping_point(&a)
+                .iter()
+                .all(|interval| interval.overlaps(&a, &a)));
+            assert_eq!(
+                at_a.len(),
+                intervals
+                    .iter()
+                    .filter(|&&(lo, hi)| lo <= a && a <= hi)
+                    .count()
+            );
+        }
+    }
+    #[test]
+    fn schedule_conflict_test() {
+        let mut meetings = IntervalTree::<f64>::new();
+        assert!(meetings.insert(9.0, 10.5));
+        assert!(meetings.insert(13.0, 14.0));
+        assert!(meetings.insert(13.0, 14.0));
+        assert!(!meetings.insert(16.0, 15.0));
+        assert!(!meetings.insert(f64::NAN, 15.0));
+        assert_eq!(meetings.len(), 3);
+
+        assert!(meetings.overlapping(&10.5, &11.0).len() == 1);
+        assert!(meetings.overlapping(&11.0, &12.5).is_empty());
+        assert_eq!(meetings.overlapping_point(&13.5).len(), 2);
+        assert_eq!(
+            format!("{:?}", meetings),
+            "{[9.0, 10.5], [13.0, 14.0], [13.0, 14.0]}"
+        );
+
+        assert!(meetings.delete(&13.0, &14.0));
+        assert!(!meetings.delete(&13.0, &15.0));
+        assert_eq!(meetings.overlapping_point(&13.5).len(), 1);
+        assert!(meetings.check().0);
+        assert!(meetings.delete(&13.0, &14.0));
+        assert!(meetings.delete(&9.0, &10.5));
+        assert!(meetings.is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+use super::{Color, RBTree};
+use std::fmt::{Debug, Write};
+
+enum Side {
+    Root,
+    Left,
+    Right,
+}
+
+impl<T: Debug> RBTree<T> {
+    // Graphviz graph of the tree; NIL leaves are drawn as points so that
+    // left and right children are told apart
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph RBTree {\n");
+        dot.push_str("    node [shape=circle, style=filled, fontcolor=white];\n");
+        let mut nils = 0;
+        if let Some(root) = self.root {
+            self.write_dot_node(root, &mut dot, &mut nils);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub(super) fn write_dot_node(&self, node: usize, dot: &mut String, nils: &mut usize) {
+        let current = self.node(node);
+        let fillcolor = match current.color {
+            Color::Red => "red",
+            Color::Black => "black",
+        };
+        let label = format!("{:?}", current.data)
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        let label = if current.cnt > 1 {
+            format!("{} x{}", label, current.cnt)
+        } else {
+            label
+        };
+        let _ = writeln!(
+            dot,
+            "    n{} [label=\"{}\", fillcolor={}];",
+            node, label, fillcolor
+        );
+
+        for child in [current.lchild, current.rchild] {
+            if let Some(child_node) = child {
+                let _ = writeln!(dot, "    n{} -> n{};", node, child_node);
+                self.write_dot_node(child_node, dot, nils);
+            } else {
+                let _ = writeln!(dot, "    nil{} [shape=point, fillcolor=black];", nils);
+                let _ = writeln!(dot, "    n{} -> nil{};", node, nils);
+                *nils += 1;
+            }
+        }
+    }
+
+    // tree lying on its left side: root at the left edge, right subtree above
+    //     /-- 3(B)
+    // 2(B)
+    //     \-- 1(B)
+    pub fn to_ascii_art(&self) -> String {
+        let mut art = String::new();
+        if let Some(root) = self.root {
+            self.write_ascii_node(root, "", Side::Root, &mut art);
+        }
+        art
+    }
+
+    fn write_ascii_node(&self, node: usize, prefix: &str, side: Side, art: &mut String) {
+        let current = self.node(node);
+        let (upper_prefix, connector, lower_prefix) = match side {
+            Side::Root => (String::new(), "", String::new()),
+            Side::Right => (format!("{}    ", prefix), "/-- ", format!("{}|   ", prefix)),
+            Side::Left => (
+                format!("{}|   ", prefix),
+                "\\-- ",
+                format!("{}    ", prefix),
+            ),
+        };
+
+        if let Some(rchild) = current.rchild {
+            self.write_ascii_node(rchild, &upper_prefix, Side::Right, art);
+        }
+
+        let color = match current.color {
+            Color::Red => 'R',
+            Color::Black => 'B',
+        };
+        let _ = write!(art, "{}{}{:?}({})", prefix, connector, current.data, color);
+        if current.cnt > 1 {
+            let _ = write!(art, " x{}", current.cnt);
+        }
+        art.push('\n');
+
+        if let Some(lchild) = current.lchild {
+            self.write_ascii_node(lchild, &lower_prefix, Side::Left, art);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::RBTree;
+    #[test]
+    fn to_dot_test() {
+        let mut rbt = RBTree::<u64>::new();
+        assert_eq!(
+            rbt.to_dot(),
+            "digraph RBTree {\n    node [shape=circle, style=filled, fontcolor=white];\n}\n"
+        );
+        for n in [2, 1, 3, 3] {
+            rbt.insert(n);
+        }
+        let dot = rbt.to_dot();
+        assert!(dot.contains("n0 [label=\"2\", fillcolor=black];"));
+        assert!(dot.contains("n1 [label=\"1\", fillcolor=red];"));
+        assert!(dot.contains("n2 [label=\"3 x2\", fillcolor=red];"));
+        assert!(dot.contains("n0 -> n1;"));
+        assert!(dot.contains("n0 -> n2;"));
+        assert_eq!(dot.matches("shape=point").count(), 4);
+    }
+    #[test]
+    fn to_ascii_art_test() {
+        let mut rbt = RBTree::<u64>::new();
+        assert_eq!(rbt.to_ascii_art(), "");
+        for n in 1..=6 {
+            rbt.insert(n);
+        }
+        rbt.insert(1);
+        assert_eq!(
+            rbt.to_ascii_art(),
+            [
+                "        /-- 6(R)",
+                "    /-- 5(B)",
+                "/-- 4(R)",
+                "|   \\-- 3(B)",
+                "2(B)",
+                "\\-- 1(B) x2",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
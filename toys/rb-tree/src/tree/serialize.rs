@@ -0,0 +1,48 @@
+use super::RBTree;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Debug;
+
+// serialized as a sequence of [value, count] in ascending order,
+// e.g. [[1,1],[2,3],[5,1]] in JSON for {1, 2, 2, 2, 5}
+impl<T: Serialize + Debug> Serialize for RBTree<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.node_iter())
+    }
+}
+
+// rebuilt in O(n) when the sequence is in ascending order
+// (a tree with a custom comparator needs its comparator again, so it is
+// restored with `PartialOrd` and may have to be rebuilt by the caller)
+impl<'de, T: Deserialize<'de> + PartialOrd + Debug> Deserialize<'de> for RBTree<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let items = Vec::<(T, usize)>::deserialize(deserializer)?;
+        let mut tree = RBTree::new();
+        tree.check_items(&items).map_err(D::Error::custom)?;
+        tree.build(items);
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::RBTree;
+    #[test]
+    fn serde_test() {
+        let rbt = RBTree::<u64>::from_sorted_iter([1, 2, 2, 2, 5]);
+        let json = serde_json::to_string(&rbt).unwrap();
+        assert_eq!(json, "[[1,1],[2,3],[5,1]]");
+
+        let restored: RBTree<u64> = serde_json::from_str(&json).unwrap();
+        assert!(restored.validate().is_ok());
+        assert!(restored.iter().eq(rbt.iter()));
+
+        let unsorted: RBTree<u64> = serde_json::from_str("[[5,1],[1,2],[5,1]]").unwrap();
+        assert!(unsorted.validate().is_ok());
+        assert!(unsorted.iter().eq([1, 1, 5, 5].iter()));
+
+        assert!(serde_json::from_str::<RBTree<u64>>("[[1,0]]").is_err());
+        let max = usize::MAX;
+        let overflow = format!("[[1,{max}],[2,{max}]]");
+        assert!(serde_json::from_str::<RBTree<u64>>(&overflow).is_err());
+    }
+}
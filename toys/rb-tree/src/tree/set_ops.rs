@@ -0,0 +1,172 @@
+use super::RBTree;
+use std::{cmp::Ordering, fmt::Debug};
+
+// how duplicates are treated by set operations
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Counting {
+    Multiset, // union keeps max count, intersection min, difference subtracts
+    Set,      // every value counts once
+}
+
+impl<T: Clone + Debug> RBTree<T> {
+    pub fn union(&self, other: &RBTree<T>, counting: Counting) -> RBTree<T> {
+        self.combine(other, counting, |a, b| a.max(b))
+    }
+
+    pub fn intersection(&self, other: &RBTree<T>, counting: Counting) -> RBTree<T> {
+        self.combine(other, counting, |a, b| a.min(b))
+    }
+
+    pub fn difference(&self, other: &RBTree<T>, counting: Counting) -> RBTree<T> {
+        self.combine(other, counting, |a, b| a.saturating_sub(b))
+    }
+
+    pub fn symmetric_difference(&self, other: &RBTree<T>, counting: Counting) -> RBTree<T> {
+        self.combine(other, counting, |a, b| a.abs_diff(b))
+    }
+
+    // merges both trees in order (O(n + m)), giving every value the count
+    // decided by count_of(count in self, count in other); values of self win
+    // over equal ones of other, and the result is ordered like self
+    // (values of other are sorted by the order of self first, which costs
+    // O(m log m) only if other is ordered differently, e.g. reversed)
+    fn combine(
+        &self,
+        other: &RBTree<T>,
+        counting: Counting,
+        count_of: impl Fn(usize, usize) -> usize,
+    ) -> RBTree<T> {
+        let count = |cnt: usize| match counting {
+            Counting::Multiset => cnt,
+            Counting::Set => cnt.min(1),
+        };
+
+        let mut items = vec![];
+        let mut push = |data: &T, cnt: usize| {
+            if cnt > 0 {
+                items.push((data.clone(), cnt));
+            }
+        };
+
+        let mut sorted: Vec<(&T, usize)> = other.node_iter().collect();
+        sorted.sort_by(|(a, _), (b, _)| self.compare(a, b).unwrap_or(Ordering::Equal));
+        let mut grouped: Vec<(&T, usize)> = vec![];
+        for (data, cnt) in sorted {
+            match grouped.last_mut() {
+                Some((last, last_cnt)) if self.compare(last, data) == Some(Ordering::Equal) => {
+                    *last_cnt += cnt
+                }
+                _ => grouped.push((data, cnt)),
+            }
+        }
+
+        let mut mine = self.node_iter().peekable();
+        let mut theirs = grouped.into_iter().peekable();
+        loop {
+            match (mine.peek(), theirs.peek()) {
+                (Some(&(a, a_cnt)), Some(&(b, b_cnt))) => match self.compare(a, b) {
+                    Some(Ordering::Equal) => {
+                        push(a, count_of(count(a_cnt), count(b_cnt)));
+                        mine.next();
+                        theirs.next();
+                    }
+                    Some(Ordering::Greater) => {
+                        push(b, count_of(0, count(b_cnt)));
+                        theirs.next();
+                    }
+                    _ => {
+                        push(a, count_of(count(a_cnt), 0));
+                        mine.next();
+                    }
+                },
+                (Some(&(a, a_cnt)), None) => {
+                    push(a, count_of(count(a_cnt), 0));
+                    mine.next();
+                }
+                (None, Some(&(b, b_cnt))) => {
+                    push(b, count_of(0, count(b_cnt)));
+                    theirs.next();
+                }
+                (None, None) => break,
+            }
+        }
+
+        let mut result = RBTree::with_cmp(self.cmp.clone());
+        result.build(items);
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        model_test::test_rng,
+        tree::{set_ops::Counting, RBTree},
+    };
+    use rand::prelude::*;
+    use std::collections::BTreeMap;
+    #[test]
+    fn set_ops_test() {
+        let mut rng = test_rng();
+        let (mut a, mut b) = (RBTree::<u64>::new(), RBTree::<u64>::new());
+        let (mut a_cnts, mut b_cnts) = (BTreeMap::new(), BTreeMap::new());
+        for _ in 0..3000 {
+            let n = rng.gen_range(0..1000);
+            a.insert(n);
+            *a_cnts.entry(n).or_insert(0usize) += 1;
+            let n = rng.gen_range(500..1500);
+            b.insert(n);
+            *b_cnts.entry(n).or_insert(0usize) += 1;
+        }
+
+        type CountOf = fn(usize, usize) -> usize;
+        let ops: [(&str, CountOf); 4] = [
+            ("union", |x, y| x.max(y)),
+            ("intersection", |x, y| x.min(y)),
+            ("difference", |x, y| x.saturating_sub(y)),
+            ("symmetric_difference", |x, y| x.abs_diff(y)),
+        ];
+        for (name, count_of) in ops {
+            for counting in [Counting::Multiset, Counting::Set] {
+                let result = match name {
+                    "union" => a.union(&b, counting),
+                    "intersection" => a.intersection(&b, counting),
+                    "difference" => a.difference(&b, counting),
+                    _ => a.symmetric_difference(&b, counting),
+                };
+                assert!(result.validate().is_ok());
+
+                let mut expected = vec![];
+                for n in 0..1500 {
+                    let mut x = *a_cnts.get(&n).unwrap_or(&0);
+                    let mut y = *b_cnts.get(&n).unwrap_or(&0);
+                    if counting == Counting::Set {
+                        (x, y) = (x.min(1), y.min(1));
+                    }
+                    for _ in 0..count_of(x, y) {
+                        expected.push(n);
+                    }
+                }
+                assert!(result.iter().eq(expected.iter()), "{} {:?}", name, counting);
+            }
+        }
+
+        let empty = RBTree::<u64>::new();
+        assert_eq!(a.union(&empty, Counting::Multiset).len(), a.len());
+        assert_eq!(a.intersection(&empty, Counting::Multiset).len(), 0);
+        assert_eq!(empty.difference(&a, Counting::Set).len(), 0);
+
+        // other is ordered differently: its values are looked up in the
+        // order of self, and the result is ordered like self
+        let ascending = RBTree::<u64>::from_sorted_iter([1, 2, 3]);
+        let mut descending = RBTree::<u64>::with_comparator(|a, b| b.cmp(a));
+        descending.extend([2, 3, 4, 4]);
+        let union = ascending.union(&descending, Counting::Set);
+        assert!(union.validate().is_ok());
+        assert!(union.iter().eq([1, 2, 3, 4].iter()));
+        let union = descending.union(&ascending, Counting::Multiset);
+        assert!(union.iter().eq([4, 4, 3, 2, 1].iter()));
+        let common = ascending.intersection(&descending, Counting::Set);
+        assert!(common.iter().eq([2, 3].iter()));
+    }
+}
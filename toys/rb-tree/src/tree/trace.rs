@@ -0,0 +1,112 @@
+use super::{Color, RBTree};
+use std::fmt::{self, Debug};
+
+// one thing rebalancing an insert or delete did to the tree; values are
+// kept as their Debug strings (like `Violation`), so a log outlives the tree
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    Case(&'static str), // which case of the fix-up applies from here
+    Recolor { data: String, color: Color },
+    RotateLeft { node: String, parent: String }, // node goes up over parent
+    RotateRight { node: String, parent: String },
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Case(case) => write!(f, "case: {}", case),
+            Step::Recolor { data, color } => {
+                let color = match color {
+                    Color::Red => "red",
+                    Color::Black => "black",
+                };
+                write!(f, "recolor {} {}", data, color)
+            }
+            Step::RotateLeft { node, parent } => {
+                write!(f, "rotate left: {} up over {}", node, parent)
+            }
+            Step::RotateRight { node, parent } => {
+                write!(f, "rotate right: {} up over {}", node, parent)
+            }
+        }
+    }
+}
+
+impl<T: Debug> RBTree<T> {
+    // runs op and returns its result with every step of rebalancing it took,
+    // e.g. `rbt.traced(|rbt| rbt.insert(3))`
+    pub fn traced<R>(&mut self, op: impl FnOnce(&mut Self) -> R) -> (R, Vec<Step>) {
+        let outer = self.trace.replace(vec![]);
+        let result = op(self);
+        let steps = std::mem::replace(&mut self.trace, outer).unwrap_or_default();
+        (result, steps)
+    }
+
+    // step is only built while tracing
+    pub(super) fn record(&mut self, step: impl FnOnce(&Self) -> Step) {
+        if self.trace.is_some() {
+            let step = step(self);
+            if let Some(trace) = &mut self.trace {
+                trace.push(step);
+            }
+        }
+    }
+
+    pub(super) fn set_color(&mut self, node: usize, color: Color) {
+        if self.node(node).color != color {
+            self.node_mut(node).color = color;
+            self.record(|tree| Step::Recolor {
+                data: format!("{:?}", tree.node(node).data),
+                color,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tree::{trace::Step, Color, RBTree};
+    #[test]
+    fn traced_test() {
+        let mut rbt = RBTree::<u64>::new();
+        let (inserted, steps) = rbt.traced(|rbt| rbt.insert(1));
+        assert!(inserted);
+        assert_eq!(
+            steps,
+            [Step::Recolor {
+                data: "1".to_string(),
+                color: Color::Black
+            }]
+        );
+
+        rbt.insert(2);
+        let (_, steps) = rbt.traced(|rbt| rbt.insert(3));
+        let log: Vec<String> = steps.iter().map(|step| step.to_string()).collect();
+        assert_eq!(
+            log,
+            [
+                "case: red parent, black uncle, outer child",
+                "recolor 1 red",
+                "recolor 2 black",
+                "rotate left: 2 up over 1",
+            ]
+        );
+
+        let (_, steps) = rbt.traced(|rbt| rbt.delete(&1));
+        assert!(steps.is_empty());
+        let (_, steps) = rbt.traced(|rbt| rbt.delete(&2));
+        assert!(!steps.is_empty());
+        assert!(rbt.validate().is_ok());
+
+        // the successor taking the place of a deleted node takes its color
+        let mut rbt = RBTree::<u64>::from_sorted_iter([1, 2, 3]);
+        let (_, steps) = rbt.traced(|rbt| rbt.delete(&2));
+        let log: Vec<String> = steps.iter().map(|step| step.to_string()).collect();
+        assert_eq!(log, ["recolor 3 black"]);
+        assert!(rbt.validate().is_ok());
+
+        // nothing is kept outside of `traced`
+        rbt.insert(4);
+        assert_eq!(rbt.traced(|_| ()).1, []);
+    }
+}
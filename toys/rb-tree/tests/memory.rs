@@ -0,0 +1,54 @@
+// builds and drops trees, checking that the resident memory comes back each
+// time; a test binary of its own, so other tests don't allocate at the same
+// time. trees of 2 million nodes are checked by default, and the 10 million
+// node ones, which take about 1 GB, only run by
+//   cargo test --test memory -- --ignored
+use rb_tree::RBTree;
+use std::sync::Mutex;
+
+// the tests measure the whole process, so they take turns
+static RSS_LOCK: Mutex<()> = Mutex::new(());
+
+// resident set size in bytes (Linux only)
+fn rss() -> Option<usize> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: usize = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * usize::try_from(page_size).ok()?)
+}
+
+fn build_drop(n: u64, rounds: usize) {
+    let _lock = RSS_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let Some(before) = rss() else {
+        return;
+    };
+    let mut after_drop = vec![];
+    for _ in 0..rounds {
+        let rbt = RBTree::<u64>::from_sorted_iter(0..n);
+        let usage = rbt.memory_usage();
+        assert_eq!(usage.nodes, n as usize);
+        assert!(rss().unwrap() >= before + usage.used / 2);
+        drop(rbt);
+        after_drop.push(rss().unwrap());
+    }
+    // everything but a little allocator slack is given back each round
+    for rss in after_drop {
+        assert!(
+            rss < before + 64 * 1024 * 1024,
+            "{} MiB resident after drop, {} MiB before",
+            rss >> 20,
+            before >> 20
+        );
+    }
+}
+
+#[test]
+fn build_drop_test() {
+    build_drop(2_000_000, 4);
+}
+
+#[test]
+#[ignore]
+fn build_drop_large_test() {
+    build_drop(10_000_000, 4);
+}